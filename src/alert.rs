@@ -0,0 +1,326 @@
+use crate::queue::NotificationQueue;
+use anyhow::{bail, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// Severity of an alert, ordered from least to most urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "critical" | "crit" => Ok(Severity::Critical),
+            other => bail!("Unknown severity: {}", other),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+        f.write_str(s)
+    }
+}
+
+/// What the alert is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    EpochBlockProduction,
+    EraBlockProduction,
+    ValidatorSetChange,
+    UpcomingValidatorSet,
+    ValidatorsOffline,
+    MissingHeartbeats,
+    Offence,
+    SlashReported,
+    Slashed,
+    FinalityLag,
+    GrandpaStalled,
+    GrandpaPaused,
+    GrandpaAuthoritySet,
+    BlockProductionStalled,
+    LowProductionRate,
+    EraRewardPoints,
+    WatchedEpoch,
+    WatchedRewardPoints,
+    WatchedCommission,
+    WatchedChilled,
+    WatchedSlash,
+    WatchedActiveSet,
+    BlockLengthChanged,
+    SubmitDataFeeModifier,
+    AppKeyCreated,
+    AppKeySet,
+    MatrixFullness,
+    VectorHeadStalled,
+    VectorSourceChainFrozen,
+    VectorFailedMessages,
+    VectorSyncCommittee,
+    VectorThroughput,
+    Sudo,
+    SudoKeyChanged,
+    RootOp,
+    TxPause,
+    RuntimeUpgrade,
+    MetadataDrift,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::EpochBlockProduction => "epoch_block_production",
+            AlertKind::EraBlockProduction => "era_block_production",
+            AlertKind::ValidatorSetChange => "validator_set_change",
+            AlertKind::UpcomingValidatorSet => "upcoming_validator_set",
+            AlertKind::ValidatorsOffline => "validators_offline",
+            AlertKind::MissingHeartbeats => "missing_heartbeats",
+            AlertKind::Offence => "offence",
+            AlertKind::SlashReported => "slash_reported",
+            AlertKind::Slashed => "slashed",
+            AlertKind::FinalityLag => "finality_lag",
+            AlertKind::GrandpaStalled => "grandpa_stalled",
+            AlertKind::GrandpaPaused => "grandpa_paused",
+            AlertKind::GrandpaAuthoritySet => "grandpa_authority_set",
+            AlertKind::BlockProductionStalled => "block_production_stalled",
+            AlertKind::LowProductionRate => "low_production_rate",
+            AlertKind::EraRewardPoints => "era_reward_points",
+            AlertKind::WatchedEpoch => "watched_epoch",
+            AlertKind::WatchedRewardPoints => "watched_reward_points",
+            AlertKind::WatchedCommission => "watched_commission",
+            AlertKind::WatchedChilled => "watched_chilled",
+            AlertKind::WatchedSlash => "watched_slash",
+            AlertKind::WatchedActiveSet => "watched_active_set",
+            AlertKind::BlockLengthChanged => "block_length_changed",
+            AlertKind::SubmitDataFeeModifier => "submit_data_fee_modifier",
+            AlertKind::AppKeyCreated => "app_key_created",
+            AlertKind::AppKeySet => "app_key_set",
+            AlertKind::MatrixFullness => "matrix_fullness",
+            AlertKind::VectorHeadStalled => "vector_head_stalled",
+            AlertKind::VectorSourceChainFrozen => "vector_source_chain_frozen",
+            AlertKind::VectorFailedMessages => "vector_failed_messages",
+            AlertKind::VectorSyncCommittee => "vector_sync_committee",
+            AlertKind::VectorThroughput => "vector_throughput",
+            AlertKind::Sudo => "sudo",
+            AlertKind::SudoKeyChanged => "sudo_key_changed",
+            AlertKind::RootOp => "root_op",
+            AlertKind::TxPause => "tx_pause",
+            AlertKind::RuntimeUpgrade => "runtime_upgrade",
+            AlertKind::MetadataDrift => "metadata_drift",
+        }
+    }
+}
+
+/// Whether an alert reports a new condition or the end of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// Chain position an alert refers to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AlertContext {
+    pub epoch: Option<u64>,
+    pub era: Option<u32>,
+    pub block: Option<u32>,
+}
+
+/// A single notification raised by the monitor. Sinks render it in their own format.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: Severity,
+    pub status: AlertStatus,
+    pub chain: String,
+    pub context: AlertContext,
+    pub title: String,
+    pub message: String,
+    /// Named values shown as a table by sinks that support it.
+    pub fields: Vec<(String, String)>,
+    /// Blocks worth linking to in a block explorer, with a label.
+    pub block_links: Vec<(String, u32)>,
+    /// Alerts sharing a key describe the same condition and are deduplicated together.
+    pub dedup_key: String,
+    /// Route of sinks that receive the alert instead of the default ones, if any are
+    /// configured for it, e.g. a watched validator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, severity: Severity, title: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity,
+            status: AlertStatus::Firing,
+            chain: String::new(),
+            context: AlertContext::default(),
+            title: title.into(),
+            message: String::new(),
+            fields: Vec::new(),
+            block_links: Vec::new(),
+            dedup_key: kind.as_str().to_string(),
+            route: None,
+        }
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    pub fn epoch(mut self, epoch: u64) -> Self {
+        self.context.epoch = Some(epoch);
+        self
+    }
+
+    pub fn era(mut self, era: u32) -> Self {
+        self.context.era = Some(era);
+        self
+    }
+
+    pub fn block(mut self, block: u32) -> Self {
+        self.context.block = Some(block);
+        self
+    }
+
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn block_link(mut self, label: impl Into<String>, block: u32) -> Self {
+        self.block_links.push((label.into(), block));
+        self
+    }
+
+    pub fn dedup_key(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = key.into();
+        self
+    }
+
+    pub fn route(mut self, route: impl Into<String>) -> Self {
+        self.route = Some(route.into());
+        self
+    }
+
+    /// Plain-text rendering used by sinks without rich formatting.
+    pub fn render_text(&self) -> String {
+        let mut text = format!("[{}] {}: {}", self.status_label(), self.chain, self.title);
+        if !self.message.is_empty() {
+            text.push('\n');
+            text.push_str(&self.message);
+        }
+        for (name, value) in &self.fields {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        for (label, block) in &self.block_links {
+            text.push_str(&format!("\n{}: #{}", label, block));
+        }
+        text
+    }
+
+    /// Status marker shown in front of the title.
+    pub fn status_label(&self) -> String {
+        match self.status {
+            AlertStatus::Firing => self.severity.to_string().to_uppercase(),
+            AlertStatus::Resolved => "RESOLVED".to_string(),
+        }
+    }
+
+    fn resolved(&self) -> Self {
+        let mut alert = self.clone();
+        alert.status = AlertStatus::Resolved;
+        alert
+    }
+}
+
+/// Stamps alerts with the chain name, suppresses duplicates within a window and sends
+/// "resolved" notifications when a firing condition clears.
+pub struct AlertManager {
+    queue: NotificationQueue,
+    chain: String,
+    dedup_window: Duration,
+    /// Send time of each dedup key, pruned to the window on every `fire`.
+    last_sent: HashMap<String, Instant>,
+    /// Firing conditions, with whether their firing notification was actually delivered.
+    active: HashMap<String, (Alert, bool)>,
+}
+
+impl AlertManager {
+    pub fn new(queue: NotificationQueue, chain: String, dedup_window: Duration) -> Self {
+        Self {
+            queue,
+            chain,
+            dedup_window,
+            last_sent: HashMap::new(),
+            active: HashMap::new(),
+        }
+    }
+
+    /// Raises an alert unless one with the same dedup key was sent within the window.
+    pub fn fire(&mut self, mut alert: Alert) {
+        alert.chain = self.chain.clone();
+        info!("{}", alert.render_text());
+
+        let now = Instant::now();
+        let window = self.dedup_window;
+        self.last_sent
+            .retain(|_, sent| now.duration_since(*sent) < window);
+        let duplicate = self.last_sent.contains_key(&alert.dedup_key);
+        if duplicate {
+            debug!("Suppressing duplicate alert {}", alert.dedup_key);
+            let delivered = self
+                .active
+                .get(&alert.dedup_key)
+                .is_some_and(|(_, delivered)| *delivered);
+            self.active
+                .insert(alert.dedup_key.clone(), (alert, delivered));
+            return;
+        }
+        self.last_sent.insert(alert.dedup_key.clone(), now);
+        self.queue.push(&alert);
+        self.active.insert(alert.dedup_key.clone(), (alert, true));
+    }
+
+    /// Clears the condition identified by `dedup_key`. A "resolved" notification is only sent
+    /// if the firing alert was delivered, so a flapping condition stays quiet within the window.
+    pub fn resolve(&mut self, dedup_key: &str) {
+        if let Some((alert, delivered)) = self.active.remove(dedup_key) {
+            let resolved = alert.resolved();
+            info!("{}", resolved.render_text());
+            if delivered {
+                self.queue.push(&resolved);
+            }
+        }
+    }
+
+    /// Raises a one-off alert that has no matching "resolved" state.
+    pub fn notify(&mut self, alert: Alert) {
+        let key = alert.dedup_key.clone();
+        self.fire(alert);
+        self.active.remove(&key);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::{resolve, resolve_all},
+    utils::{
+        api,
+        api::data_availability::events::{ApplicationKeyCreated, ApplicationKeySet},
+        AvailConfig, Opts,
+    },
+};
+use anyhow::Result;
+use codec::Decode;
+use futures::StreamExt;
+use log::warn;
+use sp_core::H256;
+use std::str::FromStr;
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    client::OnlineClient,
+    events::Events,
+    utils::AccountId32,
+};
+
+/// Length of the pallet and storage prefix plus the `Blake2_128` hash in an `AppKeys` key.
+const APP_KEYS_KEY_PREFIX: usize = 32 + 16;
+
+/// A registered application key.
+struct AppKey {
+    id: u32,
+    owner: AccountId32,
+    key: Vec<u8>,
+}
+
+/// Renders an application key as text, or as hex when it is not valid UTF-8.
+fn format_app_key(key: &[u8]) -> String {
+    match std::str::from_utf8(key) {
+        Ok(key) => key.to_string(),
+        Err(_) => {
+            let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("0x{}", hex)
+        }
+    }
+}
+
+/// Lists the registered application keys with their owner and creation block, optionally only
+/// those of an owner or an app ID.
+pub async fn list_app_keys(owner: Option<String>, id: Option<u32>) -> Result<()> {
+    let owner = owner
+        .map(|owner| AccountId32::from_str(&owner))
+        .transpose()?;
+
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
+
+    let block = client.blocks().at_latest().await?;
+    let storage = decode::storage_at(&client, block.hash());
+    let next_app_id = storage
+        .fetch_or_default(&api::storage().data_availability().next_app_id())
+        .await?
+        .0;
+    println!("Next app ID: {}", next_app_id);
+
+    let mut keys = Vec::new();
+    let app_keys = api::storage().data_availability().app_keys_iter();
+    let mut entries = storage.iter(&app_keys).await?;
+    while let Some(entry) = entries.next().await {
+        let (storage_key, info) = entry?;
+        let mut encoded_key = storage_key
+            .get(APP_KEYS_KEY_PREFIX..)
+            .ok_or_else(|| anyhow::anyhow!("AppKeys storage key too short"))?;
+        let key = Vec::<u8>::decode(&mut encoded_key)?;
+        let app_key = AppKey {
+            id: info.id.0,
+            owner: info.owner,
+            key,
+        };
+        if owner.as_ref().is_some_and(|owner| *owner != app_key.owner)
+            || id.is_some_and(|id| id != app_key.id)
+        {
+            continue;
+        }
+        keys.push(app_key);
+    }
+    keys.sort_by_key(|app_key| app_key.id);
+
+    let owners: Vec<AccountId32> = keys.iter().map(|app_key| app_key.owner.clone()).collect();
+    let owners = resolve_all(&client, block.hash(), &owners).await;
+
+    println!(
+        "{:<8} | {:<12} | {:<32} | owner",
+        "app id", "created at", "key"
+    );
+    for (app_key, owner) in keys.iter().zip(owners) {
+        let created_at = match created_at(&rpc, &client, app_key.id, block.number()).await {
+            Ok(Some(number)) => format!("#{}", number),
+            Ok(None) => "unknown".to_string(),
+            Err(e) => {
+                warn!(
+                    "Failed to find creation block of app {}: {:?}",
+                    app_key.id, e
+                );
+                "unknown".to_string()
+            }
+        };
+        println!(
+            "{:<8} | {:<12} | {:<32} | {}",
+            app_key.id,
+            created_at,
+            format_app_key(&app_key.key),
+            owner
+        );
+    }
+
+    Ok(())
+}
+
+/// Finds the block that created app `id`: app IDs are assigned in order, so the first block
+/// whose `NextAppId` is past `id` is found by binary search and confirmed by its
+/// `ApplicationKeyCreated` event. Keys from genesis are reported at block 0.
+async fn created_at(
+    rpc: &LegacyRpcMethods<AvailConfig>,
+    client: &OnlineClient<AvailConfig>,
+    id: u32,
+    best: u32,
+) -> Result<Option<u32>> {
+    let next_app_id_at = |number: u32| async move {
+        let block_hash = block_hash(rpc, number).await?;
+        let next_app_id = decode::storage_at(client, block_hash)
+            .fetch_or_default(&api::storage().data_availability().next_app_id())
+            .await?
+            .0;
+        Ok::<_, anyhow::Error>(next_app_id)
+    };
+
+    if next_app_id_at(0).await? > id {
+        return Ok(Some(0));
+    }
+    let (mut low, mut high) = (0, best);
+    if next_app_id_at(high).await? <= id {
+        return Ok(None);
+    }
+    // Invariant: NextAppId at `low` is at most `id`, and past it at `high`
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if next_app_id_at(mid).await? > id {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    let events = client
+        .blocks()
+        .at(block_hash(rpc, high).await?)
+        .await?
+        .events()
+        .await?;
+    let created = decode::find::<ApplicationKeyCreated>(events.iter())
+        .filter_map(Result::ok)
+        .any(|created| created.id.0 == id);
+    Ok(created.then_some(high))
+}
+
+async fn block_hash(rpc: &LegacyRpcMethods<AvailConfig>, number: u32) -> Result<H256> {
+    rpc.chain_get_block_hash(Some(number.into()))
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Block hash not found for number: {}", number))
+}
+
+/// Alerts when application keys are created or renamed.
+pub async fn check_app_keys(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    block_number: u32,
+    events: &Events<AvailConfig>,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    for created in decode::find::<ApplicationKeyCreated>(events.iter()) {
+        let created = created?;
+        let owner = resolve(client, block_hash, &created.owner).await;
+        let key = format_app_key(&created.key.0);
+        let alert = Alert::new(
+            AlertKind::AppKeyCreated,
+            Severity::Info,
+            format!("App key {} created with ID {}", key, created.id.0),
+        )
+        .field("App ID", created.id.0.to_string())
+        .field("Key", key)
+        .field("Owner", owner)
+        .block(block_number)
+        .block_link("Created in", block_number)
+        .dedup_key(format!("app_key_created:{}", created.id.0));
+        alerts.notify(alert);
+    }
+
+    for set in decode::find::<ApplicationKeySet>(events.iter()) {
+        let set = set?;
+        let info = decode::storage_at(client, block_hash)
+            .fetch(&api::storage().data_availability().app_keys(&set.new_key))
+            .await?;
+        let (old_key, new_key) = (
+            format_app_key(&set.old_key.0),
+            format_app_key(&set.new_key.0),
+        );
+        let mut alert = Alert::new(
+            AlertKind::AppKeySet,
+            Severity::Info,
+            format!("App key {} renamed to {}", old_key, new_key),
+        )
+        .field("Old key", old_key)
+        .field("New key", new_key.clone())
+        .block(block_number)
+        .block_link("Changed in", block_number)
+        .dedup_key(format!("app_key_set:{}:{}", block_number, new_key));
+        if let Some(info) = info {
+            let owner = resolve(client, block_hash, &info.owner).await;
+            alert = alert
+                .field("App ID", info.id.0.to_string())
+                .field("Owner", owner);
+        }
+        alerts.notify(alert);
+    }
+
+    Ok(())
+}
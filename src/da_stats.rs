@@ -0,0 +1,316 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve_all,
+    metrics,
+    utils::{
+        api::{
+            data_availability::{
+                calls::types::SubmitData,
+                events::{BlockLengthProposalSubmitted, DataSubmitted, SubmitDataFeeModifierSet},
+            },
+            runtime_types::{
+                da_control::{extensions::check_app_id::CheckAppId, pallet::Call as DaCall},
+                da_runtime::RuntimeCall,
+            },
+            transaction_payment::events::TransactionFeePaid,
+            utility::calls::types::{Batch, BatchAll, ForceBatch},
+        },
+        format_avail, AvailConfig, Opts, AVAIL_DECIMALS,
+    },
+};
+use anyhow::{bail, Result};
+use codec::Decode;
+use log::info;
+use std::{cmp::Reverse, collections::BTreeMap};
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    blocks::{Block, ExtrinsicDetails},
+    client::OnlineClient,
+    events::Events,
+    utils::AccountId32,
+};
+
+/// Number of submitters listed in the `da-stats` report.
+const TOP_SUBMITTERS: usize = 10;
+
+/// Submission counts, bytes and fees of an app, a submitter or a whole block.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DaTotals {
+    pub submissions: u64,
+    pub bytes: u64,
+    pub fees: u128,
+}
+
+impl DaTotals {
+    fn add(&mut self, other: &DaTotals) {
+        self.submissions += other.submissions;
+        self.bytes += other.bytes;
+        self.fees += other.fees;
+    }
+}
+
+/// Data submissions of one or more blocks, by app ID and by submitter.
+#[derive(Debug, Default)]
+pub struct DaStats {
+    pub blocks: u32,
+    pub totals: DaTotals,
+    pub apps: BTreeMap<u32, DaTotals>,
+    pub submitters: BTreeMap<AccountId32, DaTotals>,
+}
+
+impl DaStats {
+    /// Collects the `submit_data` extrinsics of a block.
+    pub async fn from_block(block: &Block<AvailConfig, OnlineClient<AvailConfig>>) -> Result<Self> {
+        let mut stats = DaStats {
+            blocks: 1,
+            ..Default::default()
+        };
+        for ext in block.extrinsics().await?.iter() {
+            let ext = ext?;
+            let events = ext.events().await?;
+            let Some(submitted) = decode::find_first::<DataSubmitted>(events.iter())? else {
+                continue;
+            };
+
+            let bytes = submitted_bytes(&ext)?;
+            // The actual fee already includes the tip
+            let fees = decode::find_first::<TransactionFeePaid>(events.iter())?
+                .map(|paid| paid.actual_fee)
+                .unwrap_or_default();
+            let totals = DaTotals {
+                submissions: decode::find::<DataSubmitted>(events.iter()).count() as u64,
+                bytes,
+                fees,
+            };
+
+            stats.totals.add(&totals);
+            stats.apps.entry(app_id(&ext)?).or_default().add(&totals);
+            stats
+                .submitters
+                .entry(submitted.who)
+                .or_default()
+                .add(&totals);
+        }
+        Ok(stats)
+    }
+
+    pub fn merge(&mut self, other: &DaStats) {
+        self.blocks += other.blocks;
+        self.totals.add(&other.totals);
+        for (app_id, totals) in &other.apps {
+            self.apps.entry(*app_id).or_default().add(totals);
+        }
+        for (who, totals) in &other.submitters {
+            self.submitters.entry(who.clone()).or_default().add(totals);
+        }
+    }
+
+    /// Submitters with the most bytes submitted, then the most submissions, ties by account.
+    pub fn top_submitters(&self, n: usize) -> Vec<(&AccountId32, &DaTotals)> {
+        let mut submitters: Vec<_> = self.submitters.iter().collect();
+        submitters.sort_by_key(|(_, totals)| Reverse((totals.bytes, totals.submissions)));
+        submitters.truncate(n);
+        submitters
+    }
+}
+
+/// Bytes of the `submit_data` calls of the extrinsic, called directly or in a `Utility` batch.
+/// Submissions wrapped in other calls, e.g. proxies, are counted without their size.
+fn submitted_bytes(ext: &ExtrinsicDetails<AvailConfig, OnlineClient<AvailConfig>>) -> Result<u64> {
+    if let Some(call) = decode::call::<SubmitData>(ext)? {
+        return Ok(call.data.0.len() as u64);
+    }
+    let calls = if let Some(batch) = decode::call::<Batch>(ext)? {
+        batch.calls
+    } else if let Some(batch) = decode::call::<BatchAll>(ext)? {
+        batch.calls
+    } else if let Some(batch) = decode::call::<ForceBatch>(ext)? {
+        batch.calls
+    } else {
+        return Ok(0);
+    };
+    Ok(calls
+        .iter()
+        .map(|call| match call {
+            RuntimeCall::DataAvailability(DaCall::submit_data { data }) => data.0.len() as u64,
+            _ => 0,
+        })
+        .sum())
+}
+
+/// App ID the extrinsic was submitted with, from its `CheckAppId` signed extension.
+fn app_id(ext: &ExtrinsicDetails<AvailConfig, OnlineClient<AvailConfig>>) -> Result<u32> {
+    let Some(extensions) = ext.signed_extensions() else {
+        return Ok(0);
+    };
+    for extension in extensions.iter() {
+        let extension = extension?;
+        if extension.name() == "CheckAppId" {
+            let CheckAppId(app_id) = CheckAppId::decode(&mut extension.bytes())?;
+            return Ok(app_id.0);
+        }
+    }
+    Ok(0)
+}
+
+fn format_totals(totals: &DaTotals) -> String {
+    format!(
+        "{} submissions, {} bytes, fees {}",
+        totals.submissions,
+        totals.bytes,
+        format_avail(totals.fees)
+    )
+}
+
+/// Reports data submissions per block and per app ID, and the top submitters, for a range of
+/// blocks.
+pub async fn print_da_stats(from: u32, to: u32) -> Result<()> {
+    if from > to {
+        bail!("from should be less than or equal to to.");
+    }
+
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
+
+    let mut stats = DaStats::default();
+    let mut last_hash = None;
+    for number in from..=to {
+        let block_hash = rpc
+            .chain_get_block_hash(Some(number.into()))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Block hash not found for number: {}", number))?;
+        let block = client.blocks().at(block_hash).await?;
+        let block_stats = DaStats::from_block(&block).await?;
+
+        if block_stats.totals.submissions > 0 {
+            println!("#{}: {}", number, format_totals(&block_stats.totals));
+            for (app_id, totals) in &block_stats.apps {
+                println!("    app {}: {}", app_id, format_totals(totals));
+            }
+        }
+        stats.merge(&block_stats);
+        last_hash = Some(block_hash);
+    }
+
+    println!(
+        "\n{} blocks ({}..={}): {}",
+        stats.blocks,
+        from,
+        to,
+        format_totals(&stats.totals)
+    );
+    println!(
+        "\n{:<8} | {:<12} | {:<14} | fees",
+        "app id", "submissions", "bytes"
+    );
+    for (app_id, totals) in &stats.apps {
+        println!(
+            "{:<8} | {:<12} | {:<14} | {}",
+            app_id,
+            totals.submissions,
+            totals.bytes,
+            format_avail(totals.fees)
+        );
+    }
+
+    let top = stats.top_submitters(TOP_SUBMITTERS);
+    if let Some(block_hash) = last_hash.filter(|_| !top.is_empty()) {
+        let accounts: Vec<AccountId32> = top.iter().map(|(who, _)| (*who).clone()).collect();
+        let names = resolve_all(&client, block_hash, &accounts).await;
+        println!("\nTop submitters:");
+        for ((_, totals), name) in top.iter().zip(names) {
+            println!("{}: {}", name, format_totals(totals));
+        }
+    }
+
+    Ok(())
+}
+
+/// Exports data submissions of every finalized block as metrics, and alerts on changes to the
+/// block length and the submission fee modifier.
+pub async fn check_da_submissions(
+    block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+    events: &Events<AvailConfig>,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    let number = block.number();
+    let stats = DaStats::from_block(block).await?;
+    if stats.totals.submissions > 0 {
+        info!(
+            "DA submissions in #{}: {}",
+            number,
+            format_totals(&stats.totals)
+        );
+    }
+
+    metrics::set_gauge(
+        "avail_da_block_submissions",
+        "Data submissions in the last finalized block",
+        &[],
+        stats.totals.submissions as f64,
+    );
+    metrics::set_gauge(
+        "avail_da_block_bytes",
+        "Bytes submitted in the last finalized block",
+        &[],
+        stats.totals.bytes as f64,
+    );
+    for (app_id, totals) in &stats.apps {
+        let app_id = app_id.to_string();
+        let labels = [("app_id", app_id.as_str())];
+        metrics::inc_counter(
+            "avail_da_submissions_total",
+            "Data submissions per app ID",
+            &labels,
+            totals.submissions as f64,
+        );
+        metrics::inc_counter(
+            "avail_da_bytes_total",
+            "Bytes submitted per app ID",
+            &labels,
+            totals.bytes as f64,
+        );
+        metrics::inc_counter(
+            "avail_da_fees_total",
+            "Fees paid for data submissions per app ID, in AVAIL",
+            &labels,
+            totals.fees as f64 / 10f64.powi(AVAIL_DECIMALS as i32),
+        );
+    }
+
+    if let Some(proposal) = decode::find_first::<BlockLengthProposalSubmitted>(events.iter())? {
+        let alert = Alert::new(
+            AlertKind::BlockLengthChanged,
+            Severity::Info,
+            format!(
+                "Block length set to {} rows x {} cols",
+                proposal.rows.0, proposal.cols.0
+            ),
+        )
+        .field("Rows", proposal.rows.0.to_string())
+        .field("Cols", proposal.cols.0.to_string())
+        .block(number)
+        .block_link("Changed in", number)
+        .dedup_key(format!("block_length_changed:{}", number));
+        alerts.notify(alert);
+    }
+
+    if let Some(modifier) = decode::find_first::<SubmitDataFeeModifierSet>(events.iter())? {
+        let alert = Alert::new(
+            AlertKind::SubmitDataFeeModifier,
+            Severity::Info,
+            "Data submission fee modifier changed",
+        )
+        .message(format!("{:?}", modifier.value))
+        .block(number)
+        .block_link("Changed in", number)
+        .dedup_key(format!("submit_data_fee_modifier:{}", number));
+        alerts.notify(alert);
+    }
+
+    Ok(())
+}
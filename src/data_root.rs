@@ -0,0 +1,399 @@
+use crate::{
+    decode,
+    utils::{
+        api,
+        api::{
+            data_availability::{calls::types::SubmitData, events::DataSubmitted},
+            runtime_types::avail_core::data_proof::message::Message,
+            vector::{calls::types::SendMessage, events::MessageSubmitted},
+        },
+        AvailConfig, Opts,
+    },
+};
+use anyhow::{bail, Result};
+use sp_core::H256;
+use sp_crypto_hashing::keccak_256;
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    blocks::Block,
+    client::OnlineClient,
+};
+
+/// Merkle root of the leaves, built like the runtime's binary Merkle tree: every leaf is hashed,
+/// pairs are hashed together and an odd node is promoted to the next layer. An empty tree has
+/// a zero root.
+pub fn merkle_root(leaves: &[H256]) -> H256 {
+    let mut layer: Vec<H256> = leaves
+        .iter()
+        .map(|leaf| H256(keccak_256(leaf.as_bytes())))
+        .collect();
+    if layer.is_empty() {
+        return H256::zero();
+    }
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => H256(keccak_256(&[left.as_bytes(), right.as_bytes()].concat())),
+                [node] => *node,
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+    }
+    layer[0]
+}
+
+/// Data root committed in the header: the hash of the blob root, over the `keccak_256` of every
+/// submitted data, and the bridge root, over the `keccak_256` of every ABI encoded message.
+pub fn data_root(blob_leaves: &[H256], bridge_leaves: &[H256]) -> H256 {
+    let (blob_root, bridge_root) = (merkle_root(blob_leaves), merkle_root(bridge_leaves));
+    H256(keccak_256(
+        &[blob_root.as_bytes(), bridge_root.as_bytes()].concat(),
+    ))
+}
+
+/// Bridge message as the Ethereum contracts see it.
+pub struct AddressedMessage<'a> {
+    pub message: &'a Message,
+    pub from: H256,
+    pub to: H256,
+    pub origin_domain: u32,
+    pub destination_domain: u32,
+    pub id: u64,
+}
+
+impl AddressedMessage<'_> {
+    /// Solidity `abi.encode` of the `(bytes1, bytes32, bytes32, uint32, uint32, bytes, uint64)`
+    /// message struct.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let (message_type, data) = match self.message {
+            Message::ArbitraryMessage(data) => (0x01, data.0.clone()),
+            Message::FungibleToken { asset_id, amount } => {
+                let mut data = asset_id.as_bytes().to_vec();
+                data.extend(abi_uint(*amount));
+                (0x02, data)
+            }
+        };
+
+        let mut message_type_word = [0u8; 32];
+        message_type_word[0] = message_type;
+        // Offset of the struct, then of `data` within it after the seven head words
+        let mut encoded = abi_uint(32).to_vec();
+        encoded.extend(message_type_word);
+        encoded.extend(self.from.as_bytes());
+        encoded.extend(self.to.as_bytes());
+        encoded.extend(abi_uint(self.origin_domain.into()));
+        encoded.extend(abi_uint(self.destination_domain.into()));
+        encoded.extend(abi_uint(7 * 32));
+        encoded.extend(abi_uint(self.id.into()));
+        encoded.extend(abi_uint(data.len() as u128));
+        encoded.extend(&data);
+        encoded.resize(encoded.len() + (32 - data.len() % 32) % 32, 0);
+        encoded
+    }
+}
+
+fn abi_uint(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Result of rebuilding the data root of a block.
+pub struct Verification {
+    pub header_root: H256,
+    pub rebuilt_root: H256,
+    pub blobs: usize,
+    pub messages: usize,
+    /// Problems that prevent an exact rebuild or hint at the cause of a mismatch.
+    pub notes: Vec<String>,
+}
+
+/// Rebuilds the data root of a block from its successful data submissions and bridge messages.
+/// Data is hashed from the `submit_data` call where possible and otherwise taken from the
+/// `DataSubmitted` event, so submissions inside batch or proxy calls are covered too.
+pub async fn verify_block(
+    block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+    avail_domain: u32,
+) -> Result<Verification> {
+    let mut blob_leaves = Vec::new();
+    let mut bridge_leaves = Vec::new();
+    let mut notes = Vec::new();
+
+    for ext in block.extrinsics().await?.iter() {
+        let ext = ext?;
+        let events = ext.events().await?;
+
+        let submitted: Vec<DataSubmitted> =
+            decode::find::<DataSubmitted>(events.iter()).collect::<Result<_>>()?;
+        match (decode::call::<SubmitData>(&ext)?, submitted.as_slice()) {
+            (Some(call), [event]) => {
+                let leaf = H256(keccak_256(&call.data.0));
+                if leaf != event.data_hash {
+                    notes.push(format!(
+                        "extrinsic {}: data hash {:?} differs from the event's {:?}",
+                        ext.index(),
+                        leaf,
+                        event.data_hash
+                    ));
+                }
+                blob_leaves.push(leaf);
+            }
+            _ => blob_leaves.extend(submitted.iter().map(|event| event.data_hash)),
+        }
+
+        let sent: Vec<MessageSubmitted> =
+            decode::find::<MessageSubmitted>(events.iter()).collect::<Result<_>>()?;
+        match (decode::call::<SendMessage>(&ext)?, sent.as_slice()) {
+            (_, []) => {}
+            (Some(call), [event]) => {
+                let message = AddressedMessage {
+                    message: &call.message,
+                    from: H256(event.from.0),
+                    to: call.to,
+                    origin_domain: avail_domain,
+                    destination_domain: call.domain,
+                    id: event.message_id,
+                };
+                bridge_leaves.push(H256(keccak_256(&message.abi_encode())));
+            }
+            _ => notes.push(format!(
+                "extrinsic {}: bridge message sent from a nested call cannot be rebuilt",
+                ext.index()
+            )),
+        }
+    }
+
+    Ok(Verification {
+        header_root: block.header().data_root(),
+        rebuilt_root: data_root(&blob_leaves, &bridge_leaves),
+        blobs: blob_leaves.len(),
+        messages: bridge_leaves.len(),
+        notes,
+    })
+}
+
+/// Rebuilds the data root of every block in a range and reports those that differ from the
+/// header.
+pub async fn verify_data_roots(from: u32, to: u32) -> Result<()> {
+    if from > to {
+        bail!("from should be less than or equal to to.");
+    }
+
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
+    let avail_domain = decode::constant(&client, &api::constants().vector().avail_domain())?;
+
+    let mut mismatches = 0;
+    for number in from..=to {
+        let block_hash = rpc
+            .chain_get_block_hash(Some(number.into()))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Block hash not found for number: {}", number))?;
+        let block = client.blocks().at(block_hash).await?;
+        let verification = verify_block(&block, avail_domain).await?;
+
+        if verification.header_root != verification.rebuilt_root {
+            mismatches += 1;
+            println!(
+                "#{}: MISMATCH header {:?}, rebuilt {:?} from {} blobs and {} bridge messages",
+                number,
+                verification.header_root,
+                verification.rebuilt_root,
+                verification.blobs,
+                verification.messages
+            );
+        } else if verification.blobs + verification.messages > 0 {
+            println!(
+                "#{}: ok ({} blobs, {} bridge messages)",
+                number, verification.blobs, verification.messages
+            );
+        }
+        for note in &verification.notes {
+            println!("    {}", note);
+        }
+    }
+
+    println!(
+        "\n{} blocks ({}..={}): {} data root mismatches",
+        to - from + 1,
+        from,
+        to,
+        mismatches
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::api::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+    use sp_core::bytes::from_hex;
+
+    fn leaf(byte: u8) -> H256 {
+        H256::repeat_byte(byte)
+    }
+
+    fn hash_pair(left: H256, right: H256) -> H256 {
+        H256(keccak_256(&[left.as_bytes(), right.as_bytes()].concat()))
+    }
+
+    fn hash_leaf(leaf: H256) -> H256 {
+        H256(keccak_256(leaf.as_bytes()))
+    }
+
+    /// Decodes ABI words written one 32 byte word per entry.
+    fn words(words: &[&str]) -> Vec<u8> {
+        assert!(words.iter().all(|word| word.len() == 64));
+        from_hex(&words.concat()).expect("valid hex")
+    }
+
+    #[test]
+    fn merkle_root_of_no_leaves_is_zero() {
+        assert_eq!(merkle_root(&[]), H256::zero());
+    }
+
+    #[test]
+    fn merkle_root_of_one_leaf_is_its_hash() {
+        assert_eq!(merkle_root(&[leaf(1)]), hash_leaf(leaf(1)));
+    }
+
+    #[test]
+    fn merkle_root_promotes_odd_node() {
+        let (a, b, c) = (hash_leaf(leaf(1)), hash_leaf(leaf(2)), hash_leaf(leaf(3)));
+        assert_eq!(
+            merkle_root(&[leaf(1), leaf(2), leaf(3)]),
+            hash_pair(hash_pair(a, b), c)
+        );
+
+        let (d, e) = (hash_leaf(leaf(4)), hash_leaf(leaf(5)));
+        assert_eq!(
+            merkle_root(&[leaf(1), leaf(2), leaf(3), leaf(4), leaf(5)]),
+            hash_pair(hash_pair(hash_pair(a, b), hash_pair(c, d)), e)
+        );
+    }
+
+    #[test]
+    fn data_root_hashes_blob_and_bridge_roots() {
+        let blobs = [leaf(1), leaf(2)];
+        let messages = [leaf(3)];
+        assert_eq!(
+            data_root(&blobs, &messages),
+            hash_pair(merkle_root(&blobs), merkle_root(&messages))
+        );
+        // Blocks without bridge messages commit to a zero bridge root
+        assert_eq!(
+            data_root(&blobs, &[]),
+            hash_pair(merkle_root(&blobs), H256::zero())
+        );
+        assert_eq!(data_root(&[], &[]), hash_pair(H256::zero(), H256::zero()));
+    }
+
+    #[test]
+    fn abi_encodes_arbitrary_message() {
+        let message = Message::ArbitraryMessage(BoundedVec(b"hello".to_vec()));
+        let encoded = AddressedMessage {
+            message: &message,
+            from: leaf(0xaa),
+            to: leaf(0xbb),
+            origin_domain: 1,
+            destination_domain: 2,
+            id: 0x0000_0123_0000_0004,
+        }
+        .abi_encode();
+
+        assert_eq!(
+            encoded,
+            words(&[
+                // Offset of the struct
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                // Message type, left aligned as `bytes1`
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                // Offset of `data` within the struct
+                "00000000000000000000000000000000000000000000000000000000000000e0",
+                "0000000000000000000000000000000000000000000000000000012300000004",
+                // `data`: length, then the bytes padded to a whole word
+                "0000000000000000000000000000000000000000000000000000000000000005",
+                "68656c6c6f000000000000000000000000000000000000000000000000000000",
+            ])
+        );
+    }
+
+    #[test]
+    fn abi_encodes_fungible_token() {
+        let message = Message::FungibleToken {
+            asset_id: leaf(0x11),
+            amount: 1_000_000_000_000_000_000,
+        };
+        let encoded = AddressedMessage {
+            message: &message,
+            from: leaf(0xaa),
+            to: leaf(0xbb),
+            origin_domain: 1,
+            destination_domain: 2,
+            id: 7,
+        }
+        .abi_encode();
+
+        assert_eq!(
+            encoded,
+            words(&[
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0200000000000000000000000000000000000000000000000000000000000000",
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "00000000000000000000000000000000000000000000000000000000000000e0",
+                "0000000000000000000000000000000000000000000000000000000000000007",
+                // `data` is the asset ID and the amount, two whole words
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "1111111111111111111111111111111111111111111111111111111111111111",
+                "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            ])
+        );
+    }
+
+    /// Block recorded from a live chain, for checking the rebuild against a real header.
+    #[derive(serde::Deserialize)]
+    struct RecordedBlock {
+        /// `data_root` from the block header.
+        data_root: H256,
+        /// Data of every successful submission, in extrinsic order, as hex.
+        blobs: Vec<String>,
+        /// Bridge root of the block as published to the Ethereum contracts.
+        bridge_root: H256,
+        /// `keccak_256` of every ABI encoded bridge message, in extrinsic order.
+        bridge_leaves: Vec<H256>,
+    }
+
+    /// Rebuilds the data root of a recorded block. No block is recorded in the tree as the
+    /// fixture has to come from a live node: point `DATA_ROOT_FIXTURE` at a JSON
+    /// [`RecordedBlock`] and run with `--ignored`.
+    #[test]
+    #[ignore = "needs a recorded block in DATA_ROOT_FIXTURE"]
+    fn data_root_matches_recorded_block() {
+        let path = std::env::var("DATA_ROOT_FIXTURE").expect("DATA_ROOT_FIXTURE must be set");
+        let fixture = std::fs::read_to_string(&path).expect("readable fixture");
+        let block: RecordedBlock = serde_json::from_str(&fixture).expect("valid fixture");
+
+        let blob_leaves: Vec<H256> = block
+            .blobs
+            .iter()
+            .map(|blob| H256(keccak_256(&from_hex(blob).expect("hex blob"))))
+            .collect();
+        assert_eq!(merkle_root(&block.bridge_leaves), block.bridge_root);
+        assert_eq!(
+            data_root(&blob_leaves, &block.bridge_leaves),
+            block.data_root
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use crate::utils::AvailConfig;
+use anyhow::{anyhow, Result};
+use codec::Decode;
+use futures::{Stream, StreamExt};
+use log::warn;
+use sp_core::H256;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::{Mutex, OnceLock},
+};
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, StreamOfResults},
+    blocks::{ExtrinsicDetails, StaticExtrinsic},
+    client::OnlineClient,
+    constants::ConstantAddress,
+    events::{EventDetails, StaticEvent},
+    ext::scale_value::{self, Composite, Value},
+    storage::{address::Yes, Storage, StorageAddress},
+    Metadata,
+};
+
+/// Metadata the static `api` types were generated from.
+const BUNDLED_METADATA: &[u8] = include_bytes!("../artifacts/polkadot_metadata.scale");
+
+static BUNDLED: OnceLock<Metadata> = OnceLock::new();
+
+/// Events, storage entries and constants already reported as stale.
+static STALE: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn bundled() -> &'static Metadata {
+    BUNDLED.get_or_init(|| {
+        Metadata::decode(&mut &BUNDLED_METADATA[..]).expect("bundled metadata is valid")
+    })
+}
+
+/// Warns once per item that its static types no longer match the runtime.
+fn warn_stale(item: String, error: &impl Debug) {
+    let mut stale = STALE
+        .get_or_init(Default::default)
+        .lock()
+        .expect("stale types lock poisoned");
+    if stale.insert(item.clone()) {
+        warn!(
+            "Static types for {} are stale, decoding it dynamically from the live metadata: {:?}",
+            item, error
+        );
+    }
+}
+
+/// Encodes a dynamically decoded value in the bundled metadata's layout of `type_id`, matching
+/// fields by name so fields added by the runtime are dropped, and decodes it as the static type.
+fn from_value<T: Decode>(value: &Value<u32>, type_id: u32) -> Result<T> {
+    let mut bytes = Vec::new();
+    scale_value::scale::encode_as_type(value, type_id, bundled().types(), &mut bytes)?;
+    Ok(T::decode(&mut &bytes[..])?)
+}
+
+/// Encodes the dynamic field values of an event or call in the bundled layout of its fields,
+/// `(name, type)` in order, matching them by name, and decodes the result as the static type.
+fn from_fields<'a, T: Decode>(
+    item: &str,
+    values: &Composite<u32>,
+    fields: impl Iterator<Item = (Option<&'a String>, u32)>,
+) -> Result<T> {
+    let mut bytes = Vec::new();
+    for (i, (name, type_id)) in fields.enumerate() {
+        let value = match (values, name) {
+            (Composite::Named(named), Some(name)) => named
+                .iter()
+                .find(|(value_name, _)| value_name == name)
+                .map(|(_, value)| value),
+            (Composite::Named(named), None) => named.get(i).map(|(_, value)| value),
+            (Composite::Unnamed(unnamed), _) => unnamed.get(i),
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "{} no longer has field {}",
+                item,
+                name.cloned().unwrap_or_else(|| i.to_string())
+            )
+        })?;
+        scale_value::scale::encode_as_type(value, type_id, bundled().types(), &mut bytes)?;
+    }
+    Ok(T::decode(&mut &bytes[..])?)
+}
+
+fn event_from_fields<E: StaticEvent + Decode>(event: &EventDetails<AvailConfig>) -> Result<E> {
+    let item = format!("Event {}::{}", E::PALLET, E::EVENT);
+    let variant = bundled()
+        .pallet_by_name(E::PALLET)
+        .and_then(|pallet| pallet.event_variants())
+        .and_then(|variants| variants.iter().find(|variant| variant.name == E::EVENT))
+        .ok_or_else(|| anyhow!("{} is not bundled", item))?;
+    let fields = variant
+        .fields
+        .iter()
+        .map(|field| (field.name.as_ref(), field.ty.id));
+    from_fields(&item, &event.field_values()?, fields)
+}
+
+/// Events of type `E`, decoded with the static types or, when those no longer match the
+/// runtime, from the dynamic value of the event.
+pub fn find<E: StaticEvent + Decode>(
+    events: impl Iterator<Item = Result<EventDetails<AvailConfig>, subxt::Error>>,
+) -> impl Iterator<Item = Result<E>> {
+    events.filter_map(|event| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => return Some(Err(e.into())),
+        };
+        match event.as_event::<E>() {
+            Ok(decoded) => decoded.map(Ok),
+            Err(e) => {
+                warn_stale(format!("event {}::{}", E::PALLET, E::EVENT), &e);
+                Some(event_from_fields::<E>(&event))
+            }
+        }
+    })
+}
+
+/// First event of type `E`, see [`find`].
+pub fn find_first<E: StaticEvent + Decode>(
+    events: impl Iterator<Item = Result<EventDetails<AvailConfig>, subxt::Error>>,
+) -> Result<Option<E>> {
+    find::<E>(events).next().transpose()
+}
+
+/// Whether there is an event of type `E`, see [`find`].
+pub fn has<E: StaticEvent + Decode>(
+    events: impl Iterator<Item = Result<EventDetails<AvailConfig>, subxt::Error>>,
+) -> Result<bool> {
+    Ok(find_first::<E>(events)?.is_some())
+}
+
+/// The extrinsic as call `C`, decoded with the static types or, when those no longer match the
+/// runtime, from the dynamic value of its fields. `None` when it is another call.
+pub fn call<C: StaticExtrinsic + Decode>(
+    ext: &ExtrinsicDetails<AvailConfig, OnlineClient<AvailConfig>>,
+) -> Result<Option<C>> {
+    let e = match ext.as_extrinsic::<C>() {
+        Ok(call) => return Ok(call),
+        Err(e) => e,
+    };
+    let item = format!("call {}::{}", C::PALLET, C::CALL);
+    warn_stale(item.clone(), &e);
+
+    let variant = bundled()
+        .pallet_by_name(C::PALLET)
+        .and_then(|pallet| pallet.call_variant_by_name(C::CALL))
+        .ok_or_else(|| anyhow!("Call {}::{} is not bundled", C::PALLET, C::CALL))?;
+    let fields = variant
+        .fields
+        .iter()
+        .map(|field| (field.name.as_ref(), field.ty.id));
+    from_fields(&item, &ext.field_values()?, fields).map(Some)
+}
+
+/// A constant, decoded with the static types or, when those no longer match the runtime, from
+/// its dynamic value.
+pub fn constant<Addr>(client: &OnlineClient<AvailConfig>, address: &Addr) -> Result<Addr::Target>
+where
+    Addr: ConstantAddress,
+    Addr::Target: Decode,
+{
+    let e = match client.constants().at(address) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    warn_stale(
+        format!(
+            "constant {}::{}",
+            address.pallet_name(),
+            address.constant_name()
+        ),
+        &e,
+    );
+
+    let missing = || {
+        anyhow!(
+            "Constant {}::{} not found",
+            address.pallet_name(),
+            address.constant_name()
+        )
+    };
+    let live = client.metadata();
+    let constant = live
+        .pallet_by_name(address.pallet_name())
+        .and_then(|pallet| pallet.constant_by_name(address.constant_name()))
+        .ok_or_else(missing)?;
+    let value =
+        scale_value::scale::decode_as_type(&mut constant.value(), constant.ty(), live.types())?;
+    let type_id = bundled()
+        .pallet_by_name(address.pallet_name())
+        .and_then(|pallet| pallet.constant_by_name(address.constant_name()))
+        .ok_or_else(missing)?
+        .ty();
+    from_value(&value, type_id)
+}
+
+/// Storage at a block, read with the static types or, when those no longer match the runtime,
+/// decoded dynamically from the live metadata.
+pub struct StorageAt<'a> {
+    client: &'a OnlineClient<AvailConfig>,
+    block_hash: H256,
+    storage: Storage<AvailConfig, OnlineClient<AvailConfig>>,
+}
+
+pub fn storage_at(client: &OnlineClient<AvailConfig>, block_hash: H256) -> StorageAt<'_> {
+    StorageAt {
+        client,
+        block_hash,
+        storage: client.storage().at(block_hash),
+    }
+}
+
+/// Value type and default bytes of a storage entry.
+fn storage_entry(metadata: &Metadata, address: &impl StorageAddress) -> Result<(u32, Vec<u8>)> {
+    metadata
+        .pallet_by_name(address.pallet_name())
+        .and_then(|pallet| pallet.storage())
+        .and_then(|storage| storage.entry_by_name(address.entry_name()))
+        .map(|entry| {
+            (
+                entry.entry_type().value_ty(),
+                entry.default_bytes().to_vec(),
+            )
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "Storage {}::{} not found",
+                address.pallet_name(),
+                address.entry_name()
+            )
+        })
+}
+
+impl StorageAt<'_> {
+    pub async fn fetch<Addr>(&self, address: &Addr) -> Result<Option<Addr::Target>>
+    where
+        Addr: StorageAddress<IsFetchable = Yes>,
+        Addr::Target: Decode,
+    {
+        match self.storage.fetch(address).await {
+            Ok(value) => Ok(value),
+            Err(e @ (subxt::Error::Metadata(_) | subxt::Error::Decode(_))) => {
+                self.warn_stale(address, &e);
+                self.fetch_dynamic(address).await
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn fetch_or_default<Addr>(&self, address: &Addr) -> Result<Addr::Target>
+    where
+        Addr: StorageAddress<IsFetchable = Yes, IsDefaultable = Yes>,
+        Addr::Target: Decode,
+    {
+        match self.storage.fetch_or_default(address).await {
+            Ok(value) => Ok(value),
+            Err(e @ (subxt::Error::Metadata(_) | subxt::Error::Decode(_))) => {
+                self.warn_stale(address, &e);
+                match self.fetch_dynamic(address).await? {
+                    Some(value) => Ok(value),
+                    None => {
+                        let (_, default) = storage_entry(bundled(), address)?;
+                        Ok(Addr::Target::decode(&mut &default[..])?)
+                    }
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Values at many addresses, read with a single `state_queryStorageAt` call. Each value is
+    /// decoded on its own, so one that fails to decode does not fail the others.
+    pub async fn fetch_many<Addr>(
+        &self,
+        rpc: &LegacyRpcMethods<AvailConfig>,
+        addresses: &[Addr],
+    ) -> Result<Vec<Result<Option<Addr::Target>>>>
+    where
+        Addr: StorageAddress<IsFetchable = Yes>,
+        Addr::Target: Decode,
+    {
+        let keys = addresses
+            .iter()
+            .map(|address| Ok(self.client.storage().address_bytes(address)?))
+            .collect::<Result<Vec<_>>>()?;
+        let mut values: HashMap<Vec<u8>, Vec<u8>> = rpc
+            .state_query_storage_at(keys.iter().map(Vec::as_slice), Some(self.block_hash))
+            .await?
+            .into_iter()
+            .flat_map(|change_set| change_set.changes)
+            .filter_map(|(key, value)| Some((key.0, value?.0)))
+            .collect();
+
+        Ok(addresses
+            .iter()
+            .zip(&keys)
+            .map(|(address, key)| {
+                values
+                    .remove(key)
+                    .map(|bytes| self.decode_value(address, &bytes))
+                    .transpose()
+            })
+            .collect())
+    }
+
+    /// Entries under an iterable address, as raw keys and values decoded like [`Self::fetch`].
+    pub async fn iter<'b, Addr>(
+        &'b self,
+        address: &'b Addr,
+    ) -> Result<impl Stream<Item = Result<(Vec<u8>, Addr::Target)>> + Unpin + 'b>
+    where
+        Addr: StorageAddress<IsIterable = Yes>,
+        Addr::Target: Decode,
+    {
+        let prefix = self.client.storage().address_bytes(address)?;
+        let entries = self
+            .client
+            .backend()
+            .storage_fetch_descendant_values(prefix, self.block_hash)
+            .await?;
+        Ok(entries.map(move |entry| {
+            let entry = entry?;
+            let value = self.decode_value(address, &entry.value)?;
+            Ok((entry.key, value))
+        }))
+    }
+
+    /// Keys of the entries under an iterable address. Only the keys are read, so iterating does
+    /// not depend on the value types.
+    pub async fn keys(&self, address: &impl StorageAddress) -> Result<StreamOfResults<Vec<u8>>> {
+        let prefix = self.client.storage().address_bytes(address)?;
+        Ok(self.storage.fetch_raw_keys(prefix).await?)
+    }
+
+    fn warn_stale(&self, address: &impl StorageAddress, error: &impl Debug) {
+        warn_stale(
+            format!(
+                "storage {}::{}",
+                address.pallet_name(),
+                address.entry_name()
+            ),
+            error,
+        );
+    }
+
+    async fn fetch_dynamic<Addr>(&self, address: &Addr) -> Result<Option<Addr::Target>>
+    where
+        Addr: StorageAddress,
+        Addr::Target: Decode,
+    {
+        let key = self.client.storage().address_bytes(address)?;
+        let Some(bytes) = self.storage.fetch_raw(key).await? else {
+            return Ok(None);
+        };
+        self.decode_dynamic(address, &bytes).map(Some)
+    }
+
+    /// Decodes with the static type while it matches the runtime, else through the live metadata.
+    fn decode_value<Addr>(&self, address: &Addr, bytes: &[u8]) -> Result<Addr::Target>
+    where
+        Addr: StorageAddress,
+        Addr::Target: Decode,
+    {
+        let live_hash = self
+            .client
+            .metadata()
+            .pallet_by_name(address.pallet_name())
+            .and_then(|pallet| pallet.storage_hash(address.entry_name()));
+        match address.validation_hash() {
+            Some(hash) if live_hash != Some(hash) => {
+                self.warn_stale(address, &"storage type hash changed");
+                self.decode_dynamic(address, bytes)
+            }
+            _ => Ok(Addr::Target::decode(&mut &bytes[..])?),
+        }
+    }
+
+    fn decode_dynamic<Addr>(&self, address: &Addr, bytes: &[u8]) -> Result<Addr::Target>
+    where
+        Addr: StorageAddress,
+        Addr::Target: Decode,
+    {
+        let live = self.client.metadata();
+        let (live_type, _) = storage_entry(&live, address)?;
+        let value = scale_value::scale::decode_as_type(&mut &bytes[..], live_type, live.types())?;
+        let (bundled_type, _) = storage_entry(bundled(), address)?;
+        from_value(&value, bundled_type)
+    }
+}
@@ -1,40 +1,40 @@
 #![allow(dead_code)]
 
-use crate::utils::{
-    api,
-    api::{
-        runtime_types::pallet_identity::types::Data, session::events::NewSession,
-        staking::events::EraPaid,
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    header::{DaContent, DaUsage},
+    utils::{
+        api,
+        api::{
+            runtime_types::avail_core::header::extension::HeaderExtension,
+            session::events::NewSession, staking::events::EraPaid,
+        },
+        AvailConfig, Opts,
     },
-    AvailConfig, Opts,
 };
 use anyhow::Result;
-use log::{error, info};
-use paste::paste;
-use reqwest::Client;
-use serde_json::json;
-use sp_core::H256;
-use std::{collections::HashSet, env, str::FromStr};
+use log::info;
 use structopt::StructOpt;
 use subxt::{
     backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    blocks::Block,
     client::OnlineClient,
+    events::Events,
 };
 
 const EXPECTED_BLOCKS_PER_EPOCH: u32 = 720;
 const EXPECTED_BLOCKS_PER_ERA: u32 = 4320;
 
-use std::{collections::HashMap, fs};
-
-fn load_local_map() -> HashMap<String, String> {
-    if let Ok(content) = fs::read_to_string("offchain_identities.json") {
-        // { "stash_account": "Validator Name", ... }
-        if let Ok(map) = serde_json::from_str::<HashMap<String, String>>(&content) {
-            return map;
-        }
-    }
-
-    HashMap::new()
+/// Blocks produced in a single epoch.
+#[derive(Debug, Clone, Copy)]
+struct EpochBlocks {
+    index: u64,
+    blocks: u32,
+    /// Block at which the epoch started.
+    start_block: u32,
+    /// Block at which the following epoch started.
+    next_start_block: u32,
 }
 
 /// Determines number of blocks produced in an epoch for last `n` epochs
@@ -42,30 +42,66 @@ pub async fn fetch_blocks_in_epochs(n: u32) -> Result<()> {
     let args = Opts::from_args();
 
     let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
 
     let epoch_data = blocks_in_epoch(rpc_client, n).await?;
-    for (epoch, blocks) in epoch_data {
-        println!("Epoch {} produced {} blocks", epoch, blocks);
+    for epoch in epoch_data {
+        let usage = da_usage(&rpc, epoch.start_block, epoch.next_start_block).await?;
+        println!(
+            "Epoch {} produced {} blocks: {} without app data, {} with only app 0, longest run without app data {}",
+            epoch.index, epoch.blocks, usage.empty, usage.app_zero_only, usage.longest_empty_run
+        );
     }
 
     Ok(())
 }
 
-async fn blocks_in_epoch(rpc_client: RpcClient, n: u32) -> Result<Vec<(u64, u32)>> {
+/// Classifies the blocks in `start_block..next_start_block` by their header's data lookup,
+/// walking back through parent hashes.
+async fn da_usage(
+    rpc: &LegacyRpcMethods<AvailConfig>,
+    start_block: u32,
+    next_start_block: u32,
+) -> Result<DaUsage> {
+    let mut usage = DaUsage::default();
+    if next_start_block <= start_block {
+        return Ok(usage);
+    }
+
+    let mut block_hash = rpc
+        .chain_get_block_hash(Some((next_start_block - 1).into()))
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("failed to fetch block hash"))?;
+    loop {
+        let header = rpc
+            .chain_get_header(Some(block_hash))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to fetch header {:?}", block_hash))?;
+        let HeaderExtension::V3(extension) = &header.extension;
+        usage.record(DaContent::of(&extension.app_lookup));
+
+        if header.number <= start_block {
+            break;
+        }
+        block_hash = header.parent_hash;
+    }
+
+    Ok(usage)
+}
+
+async fn blocks_in_epoch(rpc_client: RpcClient, n: u32) -> Result<Vec<EpochBlocks>> {
     let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
     // We can use the same client to drive our full Subxt interface too:
     let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
 
     // Fetch current epoch start data from the babe pallet
-    let mut current_epoch_start = client
-        .storage()
-        .at_latest()
-        .await?
+    let latest_hash = client.blocks().at_latest().await?.hash();
+    let mut current_epoch_start = decode::storage_at(&client, latest_hash)
         .fetch(&api::storage().babe().epoch_start())
         .await?
         .ok_or_else(|| anyhow::anyhow!("Failed to fetch current epoch start"))?;
 
-    let mut epoch_data: Vec<(u64, u32)> = Vec::new();
+    let mut epoch_data: Vec<EpochBlocks> = Vec::new();
 
     // Fetch previous epoch start blocks for last n epochs
     for _ in 0..n {
@@ -76,24 +112,25 @@ async fn blocks_in_epoch(rpc_client: RpcClient, n: u32) -> Result<Vec<(u64, u32)
             .ok_or_else(|| anyhow::anyhow!("failed to fetch block hash"))?;
 
         // Get the epoch index
-        let epoch = client
-            .storage()
-            .at(block_hash)
+        let epoch = decode::storage_at(&client, block_hash)
             .fetch(&api::storage().babe().epoch_index())
             .await?
             .ok_or_else(|| anyhow::anyhow!("Failed to fetch epoch"))?;
 
         // Fetch the block where the previous epoch started
-        let prev_epoch_start = client
-            .storage()
-            .at(block_hash)
+        let prev_epoch_start = decode::storage_at(&client, block_hash)
             .fetch(&api::storage().babe().epoch_start())
             .await?
             .ok_or_else(|| anyhow::anyhow!("Failed to fetch previous epoch start"))?;
 
         // Calculate the number of blocks produced in the epoch
         let blocks_in_epoch = current_epoch_start.1 - prev_epoch_start.1;
-        epoch_data.push((epoch, blocks_in_epoch));
+        epoch_data.push(EpochBlocks {
+            index: epoch,
+            blocks: blocks_in_epoch,
+            start_block: prev_epoch_start.1,
+            next_start_block: current_epoch_start.1,
+        });
 
         current_epoch_start = prev_epoch_start;
     }
@@ -101,199 +138,92 @@ async fn blocks_in_epoch(rpc_client: RpcClient, n: u32) -> Result<Vec<(u64, u32)
     Ok(epoch_data)
 }
 
-/// Monitors the chain and prints block counts when an epoch/era ends
+/// Alerts when an epoch or era ends with fewer blocks than expected and reports validator set
+/// changes when an era is paid out.
 // TODO: handle epoch 0
-pub async fn monitor_chain(channel_id: Option<String>) -> Result<()> {
-    let args = Opts::from_args();
-    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
-    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client.clone()).await?;
-
-    let constant_query = api::constants().staking().sessions_per_era();
-    let session_per_era = client.constants().at(&constant_query)?;
-    // Subscribe to all finalized blocks:
-    let mut blocks_sub = client.blocks().subscribe_finalized().await?;
-
-    // For each block, print a bunch of information about it:
-    while let Some(block) = blocks_sub.next().await {
-        let block = block?;
-
-        let events = block.events().await?;
-        if let Some(new_session) = events.find_first::<NewSession>().ok().flatten() {
-            let epoch_index = new_session.session_index;
-            info!("New epoch started: {}", epoch_index);
-
-            let epoch_data = blocks_in_epoch(rpc_client.clone(), 1).await?;
-            let last_epoch = epoch_data.first().expect("we know it exist");
-            if last_epoch.1 < EXPECTED_BLOCKS_PER_EPOCH {
-                let message = format!(
-                    "Epoch {} ended! Total blocks produced: {}",
-                    last_epoch.0, last_epoch.1
-                );
-                if let Some(ref channel) = channel_id {
-                    post_to_slack(&message, channel).await?;
-                }
-                info!("{}", message);
-            }
-        }
-
-        if let Some(era_paid) = events.find_first::<EraPaid>().ok().flatten() {
-            let era_index = era_paid.era_index;
-            let epoch_data = blocks_in_epoch(rpc_client.clone(), session_per_era).await?;
-            let total_blocks = epoch_data.iter().fold(0, |acc, e| acc + e.1);
-            if total_blocks < EXPECTED_BLOCKS_PER_ERA {
-                let message = format!(
-                    "Era {} ended! Total blocks produced: {}",
-                    era_index, total_blocks
-                );
-                if let Some(ref channel) = channel_id {
-                    post_to_slack(&message, channel).await?;
-                }
-                info!("{}", message);
-            }
-
-            // Check if there are any changes in the active set has happened
-            let current_validators = fetch_validators(client.clone(), block.hash()).await?;
-            let previous_validators =
-                fetch_validators(client.clone(), block.header().parent_hash).await?;
-
-            let added_validators: HashSet<_> = current_validators
-                .difference(&previous_validators)
-                .cloned()
-                .collect();
-            let removed_validators: HashSet<_> = previous_validators
-                .difference(&current_validators)
-                .cloned()
-                .collect();
-            if !added_validators.is_empty() || !removed_validators.is_empty() {
-                let added: Vec<String> = futures::future::join_all(
-                    added_validators
-                        .iter()
-                        .map(|acc| resolve_identity(&client, block.hash(), acc)),
-                )
-                .await
-                .into_iter()
-                .filter_map(Result::ok)
-                .collect();
-
-                let removed: Vec<String> = futures::future::join_all(
-                    removed_validators
-                        .iter()
-                        .map(|acc| resolve_identity(&client, block.hash(), acc)),
-                )
-                .await
-                .into_iter()
-                .filter_map(Result::ok)
-                .collect();
-                let change_message = format!(
-                    "Era {} validator set changes:\nAdded: {:?}\nRemoved: {:?}",
-                    era_index + 1,
-                    added,
-                    removed
-                );
-                if let Some(ref channel) = channel_id {
-                    post_to_slack(&change_message, channel).await?;
-                }
-                println!("{}", change_message);
+pub async fn check_block_production(
+    client: &OnlineClient<AvailConfig>,
+    rpc_client: &RpcClient,
+    block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+    events: &Events<AvailConfig>,
+    session_per_era: u32,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    if let Some(new_session) = decode::find_first::<NewSession>(events.iter())? {
+        let epoch_index = new_session.session_index;
+        info!("New epoch started: {}", epoch_index);
+
+        let epoch_data = blocks_in_epoch(rpc_client.clone(), 1).await?;
+        let last_epoch = epoch_data.first().expect("we know it exist");
+        if last_epoch.blocks < EXPECTED_BLOCKS_PER_EPOCH {
+            let active_era = decode::storage_at(client, block.hash())
+                .fetch(&api::storage().staking().active_era())
+                .await?;
+            let mut alert = production_alert(
+                AlertKind::EpochBlockProduction,
+                format!("Epoch {}", last_epoch.index),
+                last_epoch.blocks,
+                EXPECTED_BLOCKS_PER_EPOCH,
+                last_epoch.start_block,
+                last_epoch.next_start_block,
+            )
+            .epoch(last_epoch.index)
+            .block(block.number());
+            if let Some(active_era) = active_era {
+                alert = alert.era(active_era.index);
             }
+            alerts.fire(alert);
+        } else {
+            alerts.resolve(AlertKind::EpochBlockProduction.as_str());
         }
     }
 
-    Ok(())
-}
-
-async fn fetch_validators(
-    client: OnlineClient<AvailConfig>,
-    block_hash: H256,
-) -> Result<HashSet<String>> {
-    let validators = client
-        .storage()
-        .at(block_hash)
-        .fetch(&api::storage().session().validators())
-        .await?
-        .ok_or_else(|| anyhow::anyhow!("Failed to fetch validators"))?;
-    let validator_hash_set: HashSet<String> =
-        validators.into_iter().map(|a| a.to_string()).collect();
-    Ok(validator_hash_set)
-}
-
-async fn post_to_slack(message: &str, channel_id: &str) -> Result<()> {
-    let slack_token = env::var("SLACK_TOKEN").unwrap_or_else(|_| "MAYBE_DEFAULT".to_string());
-
-    let client = Client::new();
-
-    let payload = json!({
-        "channel": channel_id,
-        "text": message,
-    });
-
-    // Send the POST request to Slack Web API
-    let response = client
-        .post("https://slack.com/api/chat.postMessage")
-        .bearer_auth(slack_token)
-        .json(&payload)
-        .send()
-        .await?;
-
-    let status = response.status();
-    if status.is_success() {
-        info!("Message posted successfully!");
-    } else {
-        let body = response.text().await?;
-        error!(
-            "Failed to post message. Status: {:?}, Body: {}",
-            status, body
-        );
+    if let Some(era_paid) = decode::find_first::<EraPaid>(events.iter())? {
+        let era_index = era_paid.era_index;
+        let epoch_data = blocks_in_epoch(rpc_client.clone(), session_per_era).await?;
+        let total_blocks = epoch_data.iter().fold(0, |acc, e| acc + e.blocks);
+        if total_blocks < EXPECTED_BLOCKS_PER_ERA {
+            let first_epoch = epoch_data.last().expect("era has at least one epoch");
+            let last_epoch = epoch_data.first().expect("era has at least one epoch");
+            let alert = production_alert(
+                AlertKind::EraBlockProduction,
+                format!("Era {}", era_index),
+                total_blocks,
+                EXPECTED_BLOCKS_PER_ERA,
+                first_epoch.start_block,
+                last_epoch.next_start_block,
+            )
+            .era(era_index)
+            .block(block.number());
+            alerts.fire(alert);
+        } else {
+            alerts.resolve(AlertKind::EraBlockProduction.as_str());
+        }
     }
 
     Ok(())
 }
 
-macro_rules! match_raw_variants {
-    ($data:expr, $($n:literal),*) => {
-        paste! {
-            match $data {
-                $(
-                    Data::[<Raw $n>](arr) => Some(String::from_utf8_lossy(arr).to_string()),
-                )*
-                _ => None,
-            }
-        }
-    };
-}
-
-fn extract_raw_data(data: &Data) -> Option<String> {
-    match_raw_variants!(
-        data, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-        24, 25, 26, 27, 28, 29, 30, 31, 32
+/// Builds the low block production alert for an epoch or era spanning
+/// `start_block..next_start_block`.
+fn production_alert(
+    kind: AlertKind,
+    period: String,
+    blocks: u32,
+    expected: u32,
+    start_block: u32,
+    next_start_block: u32,
+) -> Alert {
+    let produced = f64::from(blocks) * 100.0 / f64::from(expected);
+    Alert::new(
+        kind,
+        Severity::Warning,
+        format!("{} ended! Total blocks produced: {}", period, blocks),
     )
-}
-
-/// Resolves the identity of an account using on-chain identity pallet or offchain file
-async fn resolve_identity(
-    client: &OnlineClient<AvailConfig>,
-    block_hash: H256,
-    account: &str,
-) -> Result<String> {
-    let account_id = subxt::utils::AccountId32::from_str(account)?;
-
-    // Try on-chain identity
-    let identity_opt = client
-        .storage()
-        .at(block_hash)
-        .fetch(&api::storage().identity().identity_of(account_id.clone()))
-        .await?;
-
-    if let Some((registration, _)) = identity_opt {
-        if let Some(display) = extract_raw_data(&registration.info.display) {
-            return Ok(format!("{} [{}]", display, account));
-        }
-    }
-
-    // Fallback to offchain identity file
-    let local_map = load_local_map();
-    if let Some(local_name) = local_map.get(account) {
-        return Ok(format!("{} [{}]", local_name, account));
-    }
-
-    Ok(format!("NO_IDENT [{}]", account))
+    .field("Period", period)
+    .field("Blocks", blocks.to_string())
+    .field("Expected", expected.to_string())
+    .field("Produced", format!("{:.2}%", produced))
+    .block_link("Start block", start_block)
+    .block_link("Next start block", next_start_block)
 }
@@ -0,0 +1,203 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve_all,
+    utils::{
+        account_from_key, api, api::staking::events::EraPaid, format_avail, AvailConfig, Opts,
+    },
+};
+use anyhow::Result;
+use sp_core::H256;
+use std::{cmp::Reverse, collections::BTreeMap};
+use structopt::StructOpt;
+use subxt::{client::OnlineClient, events::Events, utils::AccountId32};
+
+/// Reward points earned by a single validator in an era.
+pub struct ValidatorPoints {
+    /// Identity and account of the validator.
+    pub name: String,
+    pub points: u32,
+    /// 1-based rank by points, highest first.
+    pub rank: usize,
+    /// Deviation from the median in percent.
+    pub deviation: f64,
+}
+
+/// Per-validator reward points of an era.
+pub struct EraReport {
+    pub era: u32,
+    pub total_points: u32,
+    pub median_points: f64,
+    /// Total validator payout, when known.
+    pub payout: Option<u128>,
+    /// Remainder sent to the treasury, only known from the `EraPaid` event.
+    pub remainder: Option<u128>,
+    pub validators: Vec<ValidatorPoints>,
+}
+
+impl EraReport {
+    pub fn zero_point_validators(&self) -> impl Iterator<Item = &ValidatorPoints> {
+        self.validators.iter().filter(|v| v.points == 0)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Era {}: {} validators, {} points, median {:.0}",
+            self.era,
+            self.validators.len(),
+            self.total_points,
+            self.median_points
+        );
+        if let Some(payout) = self.payout {
+            out.push_str(&format!(", payout {}", format_avail(payout)));
+        }
+        if let Some(remainder) = self.remainder {
+            out.push_str(&format!(" (remainder {})", format_avail(remainder)));
+        }
+        out.push_str(&format!(
+            "\n{:<6} | {:<8} | {:<10} | validator",
+            "rank", "points", "vs median"
+        ));
+        for v in &self.validators {
+            let flag = if v.points == 0 { " (ZERO POINTS)" } else { "" };
+            out.push_str(&format!(
+                "\n{:<6} | {:<8} | {:<10} | {}{}",
+                v.rank,
+                v.points,
+                format!("{:+.1}%", v.deviation),
+                v.name,
+                flag
+            ));
+        }
+        out
+    }
+}
+
+/// Builds the reward point report of `era` from storage at `block_hash`.
+pub async fn build_era_report(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    era: u32,
+) -> Result<EraReport> {
+    let storage = decode::storage_at(client, block_hash);
+    let reward_points = storage
+        .fetch_or_default(&api::storage().staking().eras_reward_points(era))
+        .await?;
+    let mut points: BTreeMap<AccountId32, u32> = reward_points.individual.into_iter().collect();
+
+    // Validators elected for the era, so that validators without any points are listed too
+    let mut prefs = storage
+        .keys(&api::storage().staking().eras_validator_prefs_iter1(era))
+        .await?;
+    while let Some(entry) = prefs.next().await {
+        let key = entry?;
+        points.entry(account_from_key(&key)?).or_insert(0);
+    }
+
+    let mut ranked: Vec<(AccountId32, u32)> = points.into_iter().collect();
+    ranked.sort_by_key(|(_, points)| Reverse(*points));
+    let median_points = median(&ranked.iter().map(|(_, p)| *p).collect::<Vec<_>>());
+
+    let accounts: Vec<AccountId32> = ranked.iter().map(|(account, _)| account.clone()).collect();
+    let names = resolve_all(client, block_hash, &accounts).await;
+    let validators = ranked
+        .into_iter()
+        .zip(names)
+        .enumerate()
+        .map(|(i, ((_, points), name))| ValidatorPoints {
+            name,
+            points,
+            rank: i + 1,
+            deviation: if median_points > 0.0 {
+                (f64::from(points) - median_points) * 100.0 / median_points
+            } else {
+                0.0
+            },
+        })
+        .collect();
+
+    let payout = storage
+        .fetch(&api::storage().staking().eras_validator_reward(era))
+        .await?;
+
+    Ok(EraReport {
+        era,
+        total_points: reward_points.total,
+        median_points,
+        payout,
+        remainder: None,
+        validators,
+    })
+}
+
+/// Prints the reward point report of an era
+pub async fn print_era_report(era: u32) -> Result<()> {
+    let args = Opts::from_args();
+    let client = OnlineClient::<AvailConfig>::from_url(args.ws.clone()).await?;
+    let block_hash = client.backend().latest_finalized_block_ref().await?.hash();
+
+    let report = build_era_report(&client, block_hash, era).await?;
+    println!("{}", report.render());
+
+    Ok(())
+}
+
+/// Posts the reward point summary of an era when it is paid out.
+pub async fn check_era_report(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    block_number: u32,
+    events: &Events<AvailConfig>,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    let Some(era_paid) = decode::find_first::<EraPaid>(events.iter())? else {
+        return Ok(());
+    };
+
+    let mut report = build_era_report(client, block_hash, era_paid.era_index).await?;
+    report.payout = Some(era_paid.validator_payout);
+    report.remainder = Some(era_paid.remainder);
+
+    let zero_points: Vec<&str> = report
+        .zero_point_validators()
+        .map(|v| v.name.as_str())
+        .collect();
+    let severity = if zero_points.is_empty() {
+        Severity::Info
+    } else {
+        Severity::Warning
+    };
+    let alert = Alert::new(
+        AlertKind::EraRewardPoints,
+        severity,
+        format!("Era {} reward points", report.era),
+    )
+    .message(report.render())
+    .field("Validators", report.validators.len().to_string())
+    .field("Total points", report.total_points.to_string())
+    .field("Median points", format!("{:.0}", report.median_points))
+    .field("Payout", format_avail(era_paid.validator_payout))
+    .field("Remainder", format_avail(era_paid.remainder))
+    .field("Zero points", zero_points.len().to_string())
+    .era(report.era)
+    .block(block_number)
+    .block_link("Paid at", block_number)
+    .dedup_key(format!("era_reward_points:{}", report.era));
+    alerts.notify(alert);
+
+    Ok(())
+}
+
+fn median(values: &[u32]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (f64::from(sorted[mid - 1]) + f64::from(sorted[mid])) / 2.0
+    } else {
+        f64::from(sorted[mid])
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    utils::{
+        api,
+        api::grandpa::events::{NewAuthorities, Paused, Resumed},
+        AvailConfig,
+    },
+};
+use anyhow::Result;
+use log::warn;
+use sp_core::H256;
+use std::time::{Duration, Instant};
+use subxt::{client::OnlineClient, events::Events};
+
+/// Tracks how far GRANDPA finality lags behind the best block.
+pub struct FinalityMonitor {
+    max_lag_blocks: u32,
+    max_lag: Duration,
+    best_number: u32,
+    finalized_number: u32,
+    last_finalized_at: Instant,
+}
+
+impl FinalityMonitor {
+    pub fn new(max_lag_blocks: u32, max_lag: Duration) -> Self {
+        Self {
+            max_lag_blocks,
+            max_lag,
+            best_number: 0,
+            finalized_number: 0,
+            last_finalized_at: Instant::now(),
+        }
+    }
+
+    pub fn on_best(&mut self, block_number: u32) {
+        self.best_number = block_number;
+    }
+
+    pub async fn on_finalized(
+        &mut self,
+        client: &OnlineClient<AvailConfig>,
+        block_hash: H256,
+        block_number: u32,
+        finalized_at: Instant,
+        events: &Events<AvailConfig>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        self.finalized_number = block_number;
+        self.last_finalized_at = finalized_at;
+        self.check(alerts);
+
+        if decode::has::<Paused>(events.iter()).unwrap_or_else(|e| {
+            warn!("Failed to decode Grandpa::Paused event: {:?}", e);
+            false
+        }) {
+            let alert = Alert::new(
+                AlertKind::GrandpaPaused,
+                Severity::Critical,
+                format!("GRANDPA paused at block #{}", block_number),
+            )
+            .block(block_number)
+            .block_link("Paused at", block_number);
+            alerts.fire(alert);
+        }
+        if decode::has::<Resumed>(events.iter()).unwrap_or_else(|e| {
+            warn!("Failed to decode Grandpa::Resumed event: {:?}", e);
+            false
+        }) {
+            alerts.resolve(AlertKind::GrandpaPaused.as_str());
+        }
+
+        let storage = decode::storage_at(client, block_hash);
+        if let Some(new_authorities) = decode::find_first::<NewAuthorities>(events.iter())? {
+            let set_id = storage
+                .fetch_or_default(&api::storage().grandpa().current_set_id())
+                .await?;
+            let alert = Alert::new(
+                AlertKind::GrandpaAuthoritySet,
+                Severity::Info,
+                format!("New GRANDPA authority set {}", set_id),
+            )
+            .field("Set ID", set_id.to_string())
+            .field(
+                "Authorities",
+                new_authorities.authority_set.len().to_string(),
+            )
+            .block(block_number)
+            .block_link("Enacted at", block_number)
+            .dedup_key(format!("grandpa_authority_set:{}", set_id));
+            alerts.notify(alert);
+        }
+
+        match storage.fetch(&api::storage().grandpa().stalled()).await? {
+            Some((delay, median_last_finalized)) => {
+                let alert = Alert::new(
+                    AlertKind::GrandpaStalled,
+                    Severity::Critical,
+                    "GRANDPA stall noted on-chain, forced authority change scheduled",
+                )
+                .field("Delay", format!("{} blocks", delay))
+                .field(
+                    "Median last finalized",
+                    format!("#{}", median_last_finalized),
+                )
+                .block(block_number)
+                .block_link("Median last finalized", median_last_finalized);
+                alerts.fire(alert);
+            }
+            None => alerts.resolve(AlertKind::GrandpaStalled.as_str()),
+        }
+
+        Ok(())
+    }
+
+    /// Alerts when finality lags the best block by too many blocks or for too long, and
+    /// resolves the alert once it catches up.
+    pub fn check(&mut self, alerts: &mut AlertManager) {
+        let lag_blocks = self.best_number.saturating_sub(self.finalized_number);
+        let since_finalized = self.last_finalized_at.elapsed();
+        if lag_blocks <= self.max_lag_blocks && since_finalized <= self.max_lag {
+            alerts.resolve(AlertKind::FinalityLag.as_str());
+            return;
+        }
+
+        let alert = Alert::new(
+            AlertKind::FinalityLag,
+            Severity::Critical,
+            format!(
+                "Finality is lagging: {} blocks behind best, last finalized {}s ago",
+                lag_blocks,
+                since_finalized.as_secs()
+            ),
+        )
+        .field("Best", format!("#{}", self.best_number))
+        .field("Finalized", format!("#{}", self.finalized_number))
+        .field("Lag", format!("{} blocks", lag_blocks))
+        .field(
+            "Since last finalized",
+            format!("{}s", since_finalized.as_secs()),
+        )
+        .block(self.best_number)
+        .block_link("Last finalized", self.finalized_number);
+        alerts.fire(alert);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    header::{DaContent, DaUsage},
+    metrics,
+    utils::{
+        api,
+        api::{
+            runtime_types::avail_core::header::extension::HeaderExtension,
+            session::events::NewSession,
+        },
+        AvailConfig, AvailHeader,
+    },
+};
+use anyhow::Result;
+use log::debug;
+use std::collections::VecDeque;
+use subxt::{client::OnlineClient, events::Events};
+
+/// Tracks how full the data matrix of each finalized block is, between the minimum and maximum
+/// block dimensions, and alerts when blocks stay close to full, which precedes fee spikes.
+pub struct FullnessMonitor {
+    min_cells: u64,
+    max_cells: u64,
+    max_rows: u32,
+    max_cols: u32,
+    /// Number of most recent blocks the average fullness is computed over.
+    window: usize,
+    /// Average fullness over the window above which an alert fires.
+    threshold: f64,
+    /// Fullness of recent blocks and whether they were at the maximum dimensions.
+    recent: VecDeque<(f64, bool)>,
+    /// Consecutive finalized blocks without app data.
+    empty_run: u32,
+    /// Blocks without app data in the current epoch, reset on `Session::NewSession`.
+    epoch_usage: DaUsage,
+}
+
+impl FullnessMonitor {
+    pub fn new(client: &OnlineClient<AvailConfig>, window: usize, threshold: f64) -> Result<Self> {
+        let da = api::constants().data_availability();
+        let min_rows = decode::constant(client, &da.min_block_rows())?.0;
+        let min_cols = decode::constant(client, &da.min_block_cols())?.0;
+        let max_rows = decode::constant(client, &da.max_block_rows())?.0;
+        let max_cols = decode::constant(client, &da.max_block_cols())?.0;
+        Ok(Self {
+            min_cells: u64::from(min_rows) * u64::from(min_cols),
+            max_cells: u64::from(max_rows) * u64::from(max_cols),
+            max_rows,
+            max_cols,
+            window: window.max(1),
+            threshold,
+            recent: VecDeque::new(),
+            empty_run: 0,
+            epoch_usage: DaUsage::default(),
+        })
+    }
+
+    /// Exports blocks without app data, or with only app 0, and the current run of blocks
+    /// without app data, in total and for the current epoch.
+    fn record_da_content(&mut self, content: DaContent, new_epoch: bool) {
+        match content {
+            DaContent::Empty => {
+                self.empty_run += 1;
+                metrics::inc_counter(
+                    "avail_da_empty_blocks_total",
+                    "Finalized blocks without app data",
+                    &[],
+                    1.0,
+                );
+            }
+            DaContent::AppZeroOnly => {
+                self.empty_run = 0;
+                metrics::inc_counter(
+                    "avail_da_app_zero_only_blocks_total",
+                    "Finalized blocks with data only for app 0",
+                    &[],
+                    1.0,
+                );
+            }
+            DaContent::Apps => self.empty_run = 0,
+        }
+        metrics::set_gauge(
+            "avail_da_empty_run_blocks",
+            "Consecutive finalized blocks without app data up to the last one",
+            &[],
+            f64::from(self.empty_run),
+        );
+
+        if new_epoch {
+            self.epoch_usage = DaUsage::default();
+        }
+        self.epoch_usage.record(content);
+        metrics::set_gauge(
+            "avail_da_epoch_empty_blocks",
+            "Finalized blocks without app data in the current epoch",
+            &[],
+            f64::from(self.epoch_usage.empty),
+        );
+        metrics::set_gauge(
+            "avail_da_epoch_app0_only_blocks",
+            "Finalized blocks with data only for app 0 in the current epoch",
+            &[],
+            f64::from(self.epoch_usage.app_zero_only),
+        );
+        metrics::set_gauge(
+            "avail_da_epoch_longest_empty_run",
+            "Longest run of finalized blocks without app data in the current epoch",
+            &[],
+            f64::from(self.epoch_usage.longest_empty_run),
+        );
+    }
+
+    /// Share of the matrix cells between the minimum and maximum dimensions that a block uses.
+    fn fullness(&self, rows: u16, cols: u16) -> f64 {
+        let cells = u64::from(rows) * u64::from(cols);
+        if self.max_cells <= self.min_cells {
+            return 0.0;
+        }
+        let used = cells.saturating_sub(self.min_cells) as f64;
+        (used / (self.max_cells - self.min_cells) as f64).min(1.0)
+    }
+
+    /// Records the block's matrix fullness and app data. A `Session::NewSession` event in
+    /// `events` starts a new epoch, which the block is the first of.
+    pub fn on_finalized(
+        &mut self,
+        header: &AvailHeader,
+        events: &Events<AvailConfig>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let HeaderExtension::V3(extension) = &header.extension;
+        let (rows, cols) = (extension.commitment.rows, extension.commitment.cols);
+        let fullness = self.fullness(rows, cols);
+        let at_max = u32::from(rows) >= self.max_rows && u32::from(cols) >= self.max_cols;
+        debug!(
+            "Block #{} matrix {}x{}, {:.2}% full",
+            header.number,
+            rows,
+            cols,
+            fullness * 100.0
+        );
+
+        self.recent.push_back((fullness, at_max));
+        while self.recent.len() > self.window {
+            self.recent.pop_front();
+        }
+        let blocks = self.recent.len() as f64;
+        let average = self
+            .recent
+            .iter()
+            .map(|(fullness, _)| fullness)
+            .sum::<f64>()
+            / blocks;
+        let max_share = self.recent.iter().filter(|(_, at_max)| *at_max).count() as f64 / blocks;
+
+        metrics::set_gauge(
+            "avail_da_matrix_rows",
+            "Data matrix rows of the last finalized block",
+            &[],
+            f64::from(rows),
+        );
+        metrics::set_gauge(
+            "avail_da_matrix_cols",
+            "Data matrix columns of the last finalized block",
+            &[],
+            f64::from(cols),
+        );
+        metrics::set_gauge(
+            "avail_da_block_fullness",
+            "Fullness of the last finalized block's data matrix, from 0 to 1",
+            &[],
+            fullness,
+        );
+        metrics::set_gauge(
+            "avail_da_block_fullness_average",
+            "Average data matrix fullness over the fullness window",
+            &[],
+            average,
+        );
+        metrics::set_gauge(
+            "avail_da_max_dimension_share",
+            "Share of blocks in the fullness window at the maximum matrix dimensions",
+            &[],
+            max_share,
+        );
+        if at_max {
+            metrics::inc_counter(
+                "avail_da_max_dimension_blocks_total",
+                "Finalized blocks at the maximum matrix dimensions",
+                &[],
+                1.0,
+            );
+        }
+
+        let new_epoch = decode::has::<NewSession>(events.iter())?;
+        self.record_da_content(DaContent::of(&extension.app_lookup), new_epoch);
+
+        if self.recent.len() < self.window {
+            // Not observed for a full window yet
+            return Ok(());
+        }
+        if average >= self.threshold {
+            let alert = Alert::new(
+                AlertKind::MatrixFullness,
+                Severity::Warning,
+                format!(
+                    "Blocks averaged {:.0}% full over the last {} blocks",
+                    average * 100.0,
+                    self.window
+                ),
+            )
+            .field("Average fullness", format!("{:.2}%", average * 100.0))
+            .field("At max dimensions", format!("{:.2}%", max_share * 100.0))
+            .field("Threshold", format!("{:.2}%", self.threshold * 100.0))
+            .field(
+                "Max dimensions",
+                format!("{} rows x {} cols", self.max_rows, self.max_cols),
+            )
+            .block(header.number)
+            .block_link("Last block", header.number);
+            alerts.fire(alert);
+        } else {
+            alerts.resolve(AlertKind::MatrixFullness.as_str());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::utils::{
+    api::runtime_types::avail_core::{
+        data_lookup::compact::CompactDataLookup, header::extension::HeaderExtension,
+    },
+    AvailConfig, Opts,
+};
+use anyhow::Result;
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    client::OnlineClient,
+};
+
+/// Size of a compressed KZG commitment, one per row of the extended matrix.
+pub const COMMITMENT_SIZE: usize = 48;
+
+/// Rows of the erasure coded matrix per data row. The header stores the data rows, while the
+/// runtime commits to every row of the extended matrix.
+const EXTENSION_FACTOR: usize = 2;
+
+/// Start and extent, in matrix cells, of each app's data in a block.
+pub fn app_extents(lookup: &CompactDataLookup) -> Vec<(u32, u32, u32)> {
+    let mut extents = Vec::new();
+    // App 0 is left out of the compact index and fills the cells before the first entry, or
+    // the whole block when there are no other apps
+    let app_zero_end = lookup
+        .index
+        .first()
+        .map(|first| first.start)
+        .unwrap_or(lookup.size);
+    if app_zero_end > 0 {
+        extents.push((0, 0, app_zero_end));
+    }
+    for (i, item) in lookup.index.iter().enumerate() {
+        let end = lookup
+            .index
+            .get(i + 1)
+            .map(|next| next.start)
+            .unwrap_or(lookup.size);
+        extents.push((item.app_id.0, item.start, end.saturating_sub(item.start)));
+    }
+    extents
+}
+
+/// What kind of app data a block carries, from its data lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaContent {
+    /// No app data at all.
+    Empty,
+    /// Only data submitted without an app ID.
+    AppZeroOnly,
+    Apps,
+}
+
+impl DaContent {
+    pub fn of(lookup: &CompactDataLookup) -> Self {
+        if lookup.size == 0 {
+            DaContent::Empty
+        } else if lookup.index.iter().all(|item| item.app_id.0 == 0) {
+            DaContent::AppZeroOnly
+        } else {
+            DaContent::Apps
+        }
+    }
+}
+
+/// Blocks without app data in a range, such as an epoch, to tell a quiet network from a broken
+/// submission pipeline.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DaUsage {
+    pub empty: u32,
+    pub app_zero_only: u32,
+    pub longest_empty_run: u32,
+    /// Blocks without app data since the last one with app data.
+    empty_run: u32,
+}
+
+impl DaUsage {
+    /// Counts a block. Blocks must be recorded in order, either forwards or backwards.
+    pub fn record(&mut self, content: DaContent) {
+        match content {
+            DaContent::Empty => {
+                self.empty += 1;
+                self.empty_run += 1;
+                self.longest_empty_run = self.longest_empty_run.max(self.empty_run);
+            }
+            DaContent::AppZeroOnly => {
+                self.app_zero_only += 1;
+                self.empty_run = 0;
+            }
+            DaContent::Apps => self.empty_run = 0,
+        }
+    }
+}
+
+/// Decodes and prints the header extension of a block: the Kate commitment dimensions, the
+/// commitments, the data root and the app data lookup.
+pub async fn print_header(block: u32) -> Result<()> {
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
+
+    let block_hash = rpc
+        .chain_get_block_hash(Some(block.into()))
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Block hash not found for number: {}", block))?;
+    let header = client.blocks().at(block_hash).await?.header().clone();
+    let HeaderExtension::V3(extension) = &header.extension;
+    let commitment = &extension.commitment;
+
+    println!("Block #{} ({:?})", header.number, block_hash);
+    println!("Header extension: V3");
+    println!("Rows: {}", commitment.rows);
+    println!("Cols: {}", commitment.cols);
+    println!("Data root: {:?}", commitment.data_root);
+
+    let commitments = commitment.commitment.len() / COMMITMENT_SIZE;
+    let expected = commitment.rows as usize * EXTENSION_FACTOR;
+    println!(
+        "Commitment: {} bytes ({} commitments)",
+        commitment.commitment.len(),
+        commitments
+    );
+    if commitment.commitment.len() % COMMITMENT_SIZE != 0 {
+        println!(
+            "WARNING: commitment length is not a multiple of {} bytes",
+            COMMITMENT_SIZE
+        );
+    } else if commitments != expected {
+        println!(
+            "WARNING: expected one commitment per extended row ({} rows x {}), found {}",
+            commitment.rows, EXTENSION_FACTOR, commitments
+        );
+    }
+
+    let lookup = &extension.app_lookup;
+    println!("\nApp lookup: {} cells", lookup.size);
+    println!("{:<8} | {:<10} | extent", "app id", "start");
+    for (app_id, start, extent) in app_extents(lookup) {
+        println!("{:<8} | {:<10} | {}", app_id, start, extent);
+    }
+
+    Ok(())
+}
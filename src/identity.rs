@@ -0,0 +1,345 @@
+use crate::{
+    decode::{self, StorageAt},
+    utils::{
+        api,
+        api::runtime_types::pallet_identity::types::{Data, Judgement},
+        AvailConfig, Opts,
+    },
+};
+use anyhow::Result;
+use codec::Decode;
+use log::warn;
+use paste::paste;
+use sp_core::H256;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    client::OnlineClient,
+    storage::{address::Yes, StorageAddress},
+    utils::AccountId32,
+};
+use tokio::sync::OnceCell;
+
+/// Identities shared by every command and check, so each account is looked up once per TTL.
+static CACHE: OnceLock<Mutex<IdentityCache>> = OnceLock::new();
+
+struct IdentityCache {
+    ttl: Duration,
+    /// Offchain identities file, `{ "stash_account": "Validator Name", ... }`.
+    path: PathBuf,
+    local_map: Option<(HashMap<String, String>, Instant)>,
+    /// Preimages of hashed display names, `{ "0x<hash>": "Validator Name", ... }`.
+    preimages_path: Option<PathBuf>,
+    preimages: Option<(HashMap<String, String>, Instant)>,
+    names: BTreeMap<AccountId32, (String, Instant)>,
+}
+
+impl IdentityCache {
+    fn get(&self, account: &AccountId32) -> Option<&String> {
+        self.names
+            .get(account)
+            .filter(|(_, resolved_at)| resolved_at.elapsed() < self.ttl)
+            .map(|(name, _)| name)
+    }
+
+    /// Offchain identities, re-read from disk once the TTL has passed.
+    fn local_map(&mut self) -> &HashMap<String, String> {
+        if is_stale(&self.local_map, self.ttl) {
+            self.local_map = Some((load_local_map(&self.path), Instant::now()));
+        }
+        &self
+            .local_map
+            .as_ref()
+            .expect("local map was just loaded")
+            .0
+    }
+
+    /// Display name preimages, re-read from disk once the TTL has passed. Hashes are lowercase.
+    fn preimages(&mut self) -> &HashMap<String, String> {
+        if is_stale(&self.preimages, self.ttl) {
+            let preimages = match &self.preimages_path {
+                Some(path) => load_local_map(path)
+                    .into_iter()
+                    .map(|(hash, name)| (hash.to_lowercase(), name))
+                    .collect(),
+                None => HashMap::new(),
+            };
+            self.preimages = Some((preimages, Instant::now()));
+        }
+        &self
+            .preimages
+            .as_ref()
+            .expect("preimages were just loaded")
+            .0
+    }
+
+    /// Renders a display field, resolving hashed names through the preimages or showing them
+    /// as tagged hashes.
+    fn display(&mut self, data: &Data) -> Option<String> {
+        if let Some(raw) = extract_raw_data(data) {
+            return Some(raw).filter(|raw| !raw.is_empty());
+        }
+        let (tag, hash) = match data {
+            Data::BlakeTwo256(hash) => ("blake2_256", hash),
+            Data::Sha256(hash) => ("sha2_256", hash),
+            Data::Keccak256(hash) => ("keccak_256", hash),
+            Data::ShaThree256(hash) => ("sha3_256", hash),
+            _ => return None,
+        };
+        let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        let hex = format!("0x{}", hex);
+        Some(
+            self.preimages()
+                .get(&hex)
+                .cloned()
+                .unwrap_or_else(|| format!("{}:{}", tag, hex)),
+        )
+    }
+}
+
+fn is_stale(loaded: &Option<(HashMap<String, String>, Instant)>, ttl: Duration) -> bool {
+    match loaded {
+        Some((_, loaded_at)) => loaded_at.elapsed() >= ttl,
+        None => true,
+    }
+}
+
+fn cache() -> &'static Mutex<IdentityCache> {
+    CACHE.get_or_init(|| {
+        let args = Opts::from_args();
+        Mutex::new(IdentityCache {
+            ttl: Duration::from_secs(args.identity_ttl),
+            path: args.identities_file,
+            local_map: None,
+            preimages_path: args.identity_preimages,
+            preimages: None,
+            names: BTreeMap::new(),
+        })
+    })
+}
+
+fn load_local_map(path: &Path) -> HashMap<String, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+macro_rules! match_raw_variants {
+    ($data:expr, $($n:literal),*) => {
+        paste! {
+            match $data {
+                $(
+                    Data::[<Raw $n>](arr) => Some(String::from_utf8_lossy(arr).to_string()),
+                )*
+                _ => None,
+            }
+        }
+    };
+}
+
+fn extract_raw_data(data: &Data) -> Option<String> {
+    match_raw_variants!(
+        data, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+        24, 25, 26, 27, 28, 29, 30, 31, 32
+    )
+}
+
+fn judgement_name(judgement: &Judgement<u128>) -> &'static str {
+    match judgement {
+        Judgement::Unknown => "Unknown",
+        Judgement::FeePaid(_) => "FeePaid",
+        Judgement::Reasonable => "Reasonable",
+        Judgement::KnownGood => "KnownGood",
+        Judgement::OutOfDate => "OutOfDate",
+        Judgement::LowQuality => "LowQuality",
+        Judgement::Erroneous => "Erroneous",
+    }
+}
+
+/// The judgement worth showing among those given by registrars: problems first, then the
+/// strongest positive one.
+fn judgement<'a>(
+    judgements: impl IntoIterator<Item = &'a Judgement<u128>>,
+) -> Option<&'static str> {
+    let rank = |judgement: &Judgement<u128>| match judgement {
+        Judgement::Erroneous => 0,
+        Judgement::LowQuality => 1,
+        Judgement::OutOfDate => 2,
+        Judgement::KnownGood => 3,
+        Judgement::Reasonable => 4,
+        Judgement::FeePaid(_) => 5,
+        Judgement::Unknown => 6,
+    };
+    judgements
+        .into_iter()
+        .min_by_key(|judgement| rank(judgement))
+        .map(judgement_name)
+}
+
+/// Connection for the batched identity lookups, opened on first use.
+static RPC: OnceCell<LegacyRpcMethods<AvailConfig>> = OnceCell::const_new();
+
+async fn rpc() -> Result<&'static LegacyRpcMethods<AvailConfig>> {
+    RPC.get_or_try_init(|| async {
+        let args = Opts::from_args();
+        let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+        Ok(LegacyRpcMethods::new(rpc_client))
+    })
+    .await
+}
+
+/// Values at the addresses read in one batch, with `None` for accounts whose value could not
+/// be read, so a failure only affects the accounts concerned.
+async fn fetch_batch<Addr>(
+    storage: &StorageAt<'_>,
+    addresses: &[Addr],
+) -> Vec<Option<Option<Addr::Target>>>
+where
+    Addr: StorageAddress<IsFetchable = Yes>,
+    Addr::Target: Decode,
+{
+    let values = async { storage.fetch_many(rpc().await?, addresses).await };
+    match values.await {
+        Ok(values) => values
+            .into_iter()
+            .map(|value| {
+                value
+                    .map_err(|e| warn!("Failed to decode an identity: {:?}", e))
+                    .ok()
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to fetch identities: {:?}", e);
+            addresses.iter().map(|_| None).collect()
+        }
+    }
+}
+
+/// Resolves identities using the on-chain identity pallet or the offchain file, as
+/// `Name [account]`, or `Parent/Sub [account]` for sub-identities, followed by the registrar
+/// judgement such as `(KnownGood)`, or the account itself when neither knows it. Only accounts
+/// missing from the cache are looked up, in two batched reads: their sub-identities, then the
+/// registrations of them and their parents. Accounts whose lookup failed fall back to the
+/// offchain file and are retried on the next call.
+pub(crate) async fn resolve_all(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    accounts: &[AccountId32],
+) -> Vec<String> {
+    let mut missing: Vec<AccountId32> = {
+        let cache = cache().lock().expect("identity cache lock poisoned");
+        accounts
+            .iter()
+            .filter(|account| cache.get(account).is_none())
+            .cloned()
+            .collect()
+    };
+    missing.sort();
+    missing.dedup();
+
+    let mut uncached = BTreeMap::new();
+    if !missing.is_empty() {
+        let storage = decode::storage_at(client, block_hash);
+        let super_of: Vec<_> = missing
+            .iter()
+            .map(|account| api::storage().identity().super_of(account))
+            .collect();
+        let supers = fetch_batch(&storage, &super_of).await;
+
+        let mut owners: Vec<AccountId32> = missing
+            .iter()
+            .chain(supers.iter().flatten().flatten().map(|(parent, _)| parent))
+            .cloned()
+            .collect();
+        owners.sort();
+        owners.dedup();
+        let identity_of: Vec<_> = owners
+            .iter()
+            .map(|account| api::storage().identity().identity_of(account))
+            .collect();
+        let registrations: BTreeMap<&AccountId32, _> = owners
+            .iter()
+            .zip(fetch_batch(&storage, &identity_of).await)
+            .collect();
+
+        let mut cache = cache().lock().expect("identity cache lock poisoned");
+        let now = Instant::now();
+        for (account, super_of) in missing.iter().zip(supers) {
+            // Sub-identities show the parent's display name and judgements
+            let (owner, sub) = match &super_of {
+                Some(Some((parent, sub))) => (parent, Some(sub)),
+                _ => (account, None),
+            };
+            let registration = registrations.get(owner).and_then(Option::as_ref);
+            // Names are only cached once both lookups succeeded
+            let fetched = super_of.is_some() && registration.is_some();
+            let registration = registration
+                .and_then(Option::as_ref)
+                .map(|(registration, _)| registration);
+            let owner_display =
+                registration.and_then(|registration| cache.display(&registration.info.display));
+            let name = match (owner_display, sub.and_then(|sub| cache.display(sub))) {
+                (Some(parent), Some(sub)) => Some(format!("{}/{}", parent, sub)),
+                (display, _) => display,
+            };
+            let judgement = registration.and_then(|registration| {
+                judgement(
+                    registration
+                        .judgements
+                        .0
+                        .iter()
+                        .map(|(_, judgement)| judgement),
+                )
+            });
+
+            let name = name
+                .or_else(|| cache.local_map().get(&account.to_string()).cloned())
+                .unwrap_or_else(|| "NO_IDENT".to_string());
+            let mut rendered = format!("{} [{}]", name, account);
+            if let Some(judgement) = judgement {
+                rendered.push_str(&format!(" ({})", judgement));
+            }
+            if fetched {
+                cache.names.insert(account.clone(), (rendered, now));
+            } else {
+                uncached.insert(account.clone(), rendered);
+            }
+        }
+    }
+
+    let cache = cache().lock().expect("identity cache lock poisoned");
+    accounts
+        .iter()
+        .map(|account| {
+            cache
+                .names
+                .get(account)
+                .map(|(name, _)| name)
+                .or_else(|| uncached.get(account))
+                .cloned()
+                .unwrap_or_else(|| account.to_string())
+        })
+        .collect()
+}
+
+/// Identity of the account, or the account itself when it cannot be resolved.
+pub(crate) async fn resolve(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    account: &AccountId32,
+) -> String {
+    resolve_all(client, block_hash, std::slice::from_ref(account))
+        .await
+        .remove(0)
+}
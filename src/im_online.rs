@@ -0,0 +1,178 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve_all,
+    utils::{api, api::im_online::events::SomeOffline, AvailConfig},
+};
+use anyhow::Result;
+use futures::future::join_all;
+use log::{info, warn};
+use sp_core::H256;
+use subxt::{client::OnlineClient, events::Events, utils::AccountId32};
+
+/// Tracks ImOnline heartbeats and warns about validators that look offline.
+pub struct HeartbeatMonitor {
+    /// Fraction of the session after which validators with no heartbeat and no authored
+    /// blocks are reported.
+    threshold: f64,
+    epoch_duration: u64,
+    /// Session that has already been checked for missing heartbeats.
+    checked_session: Option<u32>,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(client: &OnlineClient<AvailConfig>, threshold: f64) -> Result<Self> {
+        let epoch_duration = decode::constant(client, &api::constants().babe().epoch_duration())?;
+        Ok(Self {
+            threshold,
+            epoch_duration,
+            checked_session: None,
+        })
+    }
+
+    pub async fn on_block(
+        &mut self,
+        client: &OnlineClient<AvailConfig>,
+        block_hash: H256,
+        block_number: u32,
+        events: &Events<AvailConfig>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let session_index = decode::storage_at(client, block_hash)
+            .fetch_or_default(&api::storage().session().current_index())
+            .await?;
+
+        // `SomeOffline` is emitted at the end of the session, in the block starting the next one
+        match decode::find_first::<SomeOffline>(events.iter()) {
+            Ok(Some(some_offline)) => {
+                let offline: Vec<_> = some_offline
+                    .offline
+                    .iter()
+                    .map(|(account, _)| account.clone())
+                    .collect();
+                let offline = resolve_all(client, block_hash, &offline).await;
+                let ended_session = session_index.saturating_sub(1);
+                let alert = Alert::new(
+                    AlertKind::ValidatorsOffline,
+                    Severity::Warning,
+                    format!(
+                        "{} validator(s) offline in session {}",
+                        offline.len(),
+                        ended_session
+                    ),
+                )
+                .message(offline.join("\n"))
+                .epoch(ended_session.into())
+                .block(block_number)
+                .dedup_key(format!("validators_offline:{}", ended_session));
+                alerts.notify(alert);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to decode ImOnline::SomeOffline event: {:?}", e),
+        }
+
+        if self.checked_session == Some(session_index) {
+            return Ok(());
+        }
+        let progress = self.session_progress(client, block_hash).await?;
+        if progress < self.threshold {
+            return Ok(());
+        }
+        self.checked_session = Some(session_index);
+
+        let silent = silent_validators(client, block_hash, session_index).await?;
+        info!(
+            "Session {} is {:.0}% through, {} validator(s) without heartbeat or authored blocks",
+            session_index,
+            progress * 100.0,
+            silent.len()
+        );
+        if silent.is_empty() {
+            alerts.resolve(AlertKind::MissingHeartbeats.as_str());
+            return Ok(());
+        }
+
+        let names = resolve_all(client, block_hash, &silent).await;
+        let alert = Alert::new(
+            AlertKind::MissingHeartbeats,
+            Severity::Warning,
+            format!(
+                "{} validator(s) without heartbeat or authored blocks {:.0}% into session {}",
+                names.len(),
+                progress * 100.0,
+                session_index
+            ),
+        )
+        .message(names.join("\n"))
+        .epoch(session_index.into())
+        .block(block_number);
+        alerts.fire(alert);
+
+        Ok(())
+    }
+
+    /// Fraction of the current BABE epoch (session) elapsed at `block_hash`, based on slots.
+    async fn session_progress(
+        &self,
+        client: &OnlineClient<AvailConfig>,
+        block_hash: H256,
+    ) -> Result<f64> {
+        let storage = decode::storage_at(client, block_hash);
+        let genesis_slot = storage
+            .fetch_or_default(&api::storage().babe().genesis_slot())
+            .await?;
+        let epoch_index = storage
+            .fetch_or_default(&api::storage().babe().epoch_index())
+            .await?;
+        let current_slot = storage
+            .fetch_or_default(&api::storage().babe().current_slot())
+            .await?;
+
+        let epoch_start_slot = genesis_slot
+            .0
+            .saturating_add(epoch_index.saturating_mul(self.epoch_duration));
+        let elapsed = current_slot.0.saturating_sub(epoch_start_slot);
+        Ok(elapsed as f64 / self.epoch_duration as f64)
+    }
+}
+
+/// Returns the session validators that have neither sent a heartbeat nor authored a block
+/// in `session_index`.
+async fn silent_validators(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    session_index: u32,
+) -> Result<Vec<AccountId32>> {
+    let validators = decode::storage_at(client, block_hash)
+        .fetch(&api::storage().session().validators())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to fetch validators"))?;
+
+    // ImOnline authority indices follow the order of the session validators
+    let checks = validators.iter().enumerate().map(|(auth_index, account)| {
+        let storage = decode::storage_at(client, block_hash);
+        async move {
+            let heartbeat = storage
+                .fetch(
+                    &api::storage()
+                        .im_online()
+                        .received_heartbeats(session_index, auth_index as u32),
+                )
+                .await?;
+            let authored = storage
+                .fetch_or_default(
+                    &api::storage()
+                        .im_online()
+                        .authored_blocks(session_index, account),
+                )
+                .await?;
+            Ok::<_, anyhow::Error>((heartbeat.is_none() && authored == 0).then(|| account.clone()))
+        }
+    });
+
+    join_all(checks)
+        .await
+        .into_iter()
+        .filter_map(Result::transpose)
+        .collect()
+}
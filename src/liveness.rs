@@ -0,0 +1,140 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    utils::{api, AvailConfig, AvailHeader},
+};
+use anyhow::Result;
+use codec::Decode;
+use sp_consensus_babe::{digests::PreDigest, BABE_ENGINE_ID};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use subxt::{client::OnlineClient, config::substrate::DigestItem};
+
+/// Watches the best chain for block production stalls and a low production rate, so problems
+/// are reported while the epoch is still running.
+pub struct LivenessMonitor {
+    slot_duration: Duration,
+    /// Slots without a new best block before production is considered stalled.
+    stall_slots: u32,
+    /// Number of most recent slots the production rate is computed over.
+    window_slots: u64,
+    /// Minimum share of slots in the window that should have a block.
+    min_rate: f64,
+    started_slot: u64,
+    last_block_at: Instant,
+    last_block_number: u32,
+    /// Slot of every recent best block, by block number.
+    slots: BTreeMap<u32, u64>,
+}
+
+impl LivenessMonitor {
+    pub fn new(
+        client: &OnlineClient<AvailConfig>,
+        stall_slots: u32,
+        window_slots: u64,
+        min_rate: f64,
+    ) -> Result<Self> {
+        let slot_duration =
+            decode::constant(client, &api::constants().babe().expected_block_time())?;
+        let slot_duration = Duration::from_millis(slot_duration);
+        Ok(Self {
+            slot_duration,
+            stall_slots,
+            window_slots,
+            min_rate,
+            started_slot: current_slot(slot_duration),
+            last_block_at: Instant::now(),
+            last_block_number: 0,
+            slots: BTreeMap::new(),
+        })
+    }
+
+    /// Records a new best block, `seen_at` being when its notification arrived.
+    pub fn on_best(&mut self, header: &AvailHeader, seen_at: Instant) {
+        self.last_block_at = self.last_block_at.max(seen_at);
+        self.last_block_number = header.number;
+        if let Some(slot) = babe_slot(header) {
+            // A re-org replaces the slot recorded for the same height
+            self.slots.insert(header.number, slot);
+            self.slots.retain(|number, _| *number <= header.number);
+        }
+    }
+
+    /// Checks for a stall and for a low production rate over the window.
+    pub fn check(&mut self, alerts: &mut AlertManager) {
+        let since_last_block = self.last_block_at.elapsed();
+        if since_last_block > self.slot_duration * self.stall_slots {
+            let alert = Alert::new(
+                AlertKind::BlockProductionStalled,
+                Severity::Critical,
+                format!(
+                    "No new block for {}s since #{}",
+                    since_last_block.as_secs(),
+                    self.last_block_number
+                ),
+            )
+            .field("Last block", format!("#{}", self.last_block_number))
+            .field(
+                "Since last block",
+                format!("{}s", since_last_block.as_secs()),
+            )
+            .field("Threshold", format!("{} slots", self.stall_slots))
+            .block(self.last_block_number)
+            .block_link("Last block", self.last_block_number);
+            alerts.fire(alert);
+        } else {
+            alerts.resolve(AlertKind::BlockProductionStalled.as_str());
+        }
+
+        let now_slot = current_slot(self.slot_duration);
+        if now_slot < self.started_slot + self.window_slots {
+            // Not observed for a full window yet
+            return;
+        }
+        let window_start = now_slot - self.window_slots;
+        self.slots.retain(|_, slot| *slot > window_start);
+        let produced = self.slots.len() as u64;
+        let rate = produced as f64 / self.window_slots as f64;
+        if rate < self.min_rate {
+            let alert = Alert::new(
+                AlertKind::LowProductionRate,
+                Severity::Warning,
+                format!(
+                    "Block production rate {:.0}% over the last {} slots",
+                    rate * 100.0,
+                    self.window_slots
+                ),
+            )
+            .field("Blocks", produced.to_string())
+            .field("Slots", self.window_slots.to_string())
+            .field("Rate", format!("{:.2}%", rate * 100.0))
+            .field("Threshold", format!("{:.2}%", self.min_rate * 100.0))
+            .block(self.last_block_number);
+            alerts.fire(alert);
+        } else {
+            alerts.resolve(AlertKind::LowProductionRate.as_str());
+        }
+    }
+}
+
+/// Slot of the block from its BABE pre-runtime digest.
+pub fn babe_slot(header: &AvailHeader) -> Option<u64> {
+    header.digest.logs.iter().find_map(|log| match log {
+        DigestItem::PreRuntime(engine_id, data) if *engine_id == BABE_ENGINE_ID => {
+            PreDigest::decode(&mut &data[..])
+                .ok()
+                .map(|pre_digest| u64::from(pre_digest.slot()))
+        }
+        _ => None,
+    })
+}
+
+/// Slot the wall clock is currently in.
+fn current_slot(slot_duration: Duration) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.as_millis() / slot_duration.as_millis().max(1)) as u64
+}
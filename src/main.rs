@@ -1,8 +1,31 @@
+mod alert;
+mod app_keys;
 mod avail_api;
+mod da_stats;
+mod data_root;
+mod decode;
 mod epoch_blocks;
+mod era_report;
+mod finality;
+mod fullness;
+mod header;
+mod identity;
+mod im_online;
+mod liveness;
+mod metadata;
+mod metrics;
+mod monitor;
+mod notifier;
+mod offences;
+mod privileged;
+mod queue;
+mod runtime;
 mod secondary_authors;
 mod traverse_chain;
 pub mod utils;
+mod validator_set;
+mod vector;
+mod watchlist;
 
 use log::info;
 use std::net::SocketAddr;
@@ -16,6 +39,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Health check endpoint
     let health_route = warp::path!("health").map(|| warp::reply::json(&"OK"));
+    // Prometheus metrics endpoint
+    let metrics_route = warp::path!("metrics").map(|| {
+        warp::reply::with_header(
+            metrics::render(),
+            "content-type",
+            "text/plain; version=0.0.4",
+        )
+    });
+    let routes = health_route.or(metrics_route);
 
     // Start the health check server
     let mut health_port = opts.health_port;
@@ -23,9 +55,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut bound = false;
 
     while !bound {
-        if let Ok(_) = tokio::net::TcpListener::bind(addr).await {
+        if tokio::net::TcpListener::bind(addr).await.is_ok() {
             info!("Health check server running on port {}", health_port);
-            tokio::spawn(warp::serve(health_route.clone()).run(addr));
+            tokio::spawn(warp::serve(routes).run(addr));
             bound = true;
         } else {
             health_port += 1;
@@ -46,8 +78,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Command::SecondaryAuthors { block_id } => {
             secondary_authors::find_secondary_authors(block_id).await?;
         }
-        Command::ChainMonitor { channel_id } => {
-            epoch_blocks::monitor_chain(channel_id).await?;
+        Command::EraReport { era } => {
+            era_report::print_era_report(era).await?;
+        }
+        Command::DaStats { from, to } => {
+            da_stats::print_da_stats(from, to).await?;
+        }
+        Command::AppKeys { owner, id } => {
+            app_keys::list_app_keys(owner, id).await?;
+        }
+        Command::Header { block } => {
+            header::print_header(block).await?;
+        }
+        Command::VerifyDataRoot { from, to } => {
+            data_root::verify_data_roots(from, to).await?;
+        }
+        Command::VectorMessage { query, from, to } => {
+            vector::print_message(query, from, to).await?;
+        }
+        Command::UpdateMetadata { path, dry_run } => {
+            metadata::update_metadata(&path, dry_run).await?;
+        }
+        Command::ChainMonitor(monitor_opts) => {
+            monitor::monitor_chain(monitor_opts).await?;
         }
     }
 
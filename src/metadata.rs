@@ -0,0 +1,297 @@
+use crate::utils::{AvailConfig, Opts};
+use anyhow::{anyhow, Context, Result};
+use codec::{Decode, Encode};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef};
+use sp_core::H256;
+use sp_crypto_hashing::blake2_256;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    Metadata,
+};
+
+/// Metadata version of the bundled artifact the static types are generated from.
+const METADATA_VERSION: u32 = 15;
+
+/// Pallets each monitor check decodes, to list the checks a runtime upgrade may affect.
+const CHECK_PALLETS: &[(&str, &[&str])] = &[
+    ("Runtime upgrade", &["System"]),
+    ("Finality", &["Grandpa"]),
+    ("ImOnline", &["ImOnline", "Session", "Babe"]),
+    ("Liveness", &["Babe"]),
+    ("Offence", &["Offences", "Staking", "Session"]),
+    ("Block production", &["Babe", "Session", "Staking"]),
+    ("Era report", &["Staking"]),
+    ("Validator set", &["Staking", "Session"]),
+    ("Watchlist", &["Staking", "Session", "ImOnline"]),
+    (
+        "DA submissions",
+        &["DataAvailability", "TransactionPayment"],
+    ),
+    ("Fullness", &["DataAvailability"]),
+    ("App key", &["DataAvailability"]),
+    ("Privileged call", &["Sudo", "Mandate", "TxPause"]),
+    ("Vector", &["Vector", "Staking"]),
+    ("Identity", &["Identity"]),
+];
+
+/// Items of one kind in a pallet, by name, with a fingerprint that changes with their encoding.
+type Items = BTreeMap<String, String>;
+
+struct Pallet {
+    name: String,
+    items: [(&'static str, Items); 3],
+}
+
+/// Hash of the structure of a type resolved through the registry, so that it does not depend on
+/// type ids. Types referring back to one being hashed are hashed by their path.
+fn type_hash(
+    types: &PortableRegistry,
+    id: u32,
+    visiting: &mut Vec<u32>,
+    hashes: &mut HashMap<u32, H256>,
+) -> H256 {
+    if let Some(hash) = hashes.get(&id) {
+        return *hash;
+    }
+    let Some(ty) = types.resolve(id) else {
+        return H256(blake2_256(format!("?{}", id).as_bytes()));
+    };
+    if visiting.contains(&id) {
+        return H256(blake2_256(ty.path.segments.join("::").as_bytes()));
+    }
+    visiting.push(id);
+    let mut shape_of = |id: u32| format!("{:?}", type_hash(types, id, visiting, hashes));
+    let shape = match &ty.type_def {
+        TypeDef::Composite(composite) => fields_shape(&composite.fields, &mut shape_of),
+        TypeDef::Variant(variant) => {
+            let variants: Vec<String> = variant
+                .variants
+                .iter()
+                .map(|variant| {
+                    format!(
+                        "{}#{}{}",
+                        variant.name,
+                        variant.index,
+                        fields_shape(&variant.fields, &mut shape_of)
+                    )
+                })
+                .collect();
+            format!("<{}>", variants.join("|"))
+        }
+        TypeDef::Sequence(sequence) => format!("[{}]", shape_of(sequence.type_param.id)),
+        TypeDef::Array(array) => format!("[{}; {}]", shape_of(array.type_param.id), array.len),
+        TypeDef::Tuple(tuple) => {
+            let fields: Vec<String> = tuple.fields.iter().map(|ty| shape_of(ty.id)).collect();
+            format!("({})", fields.join(", "))
+        }
+        TypeDef::Primitive(primitive) => format!("{:?}", primitive),
+        TypeDef::Compact(compact) => format!("Compact<{}>", shape_of(compact.type_param.id)),
+        TypeDef::BitSequence(bits) => format!(
+            "BitVec<{}, {}>",
+            shape_of(bits.bit_store_type.id),
+            shape_of(bits.bit_order_type.id)
+        ),
+    };
+    visiting.pop();
+    let hash = H256(blake2_256(shape.as_bytes()));
+    hashes.insert(id, hash);
+    hash
+}
+
+fn fields_shape(
+    fields: &[Field<PortableForm>],
+    shape_of: &mut impl FnMut(u32) -> String,
+) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| match &field.name {
+            Some(name) => format!("{}: {}", name, shape_of(field.ty.id)),
+            None => shape_of(field.ty.id),
+        })
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Calls are compared by their metadata hash, storage entries by the hash of their key and
+/// value types. The metadata has no per-event hash, so events are compared by a hash of the
+/// structure of their fields' types.
+fn pallets(metadata: &Metadata) -> Vec<Pallet> {
+    metadata
+        .pallets()
+        .map(|pallet| {
+            let storage = pallet
+                .storage()
+                .map(|storage| storage.entries())
+                .unwrap_or_default()
+                .iter()
+                .map(|entry| {
+                    let hash = pallet.storage_hash(entry.name()).unwrap_or_default();
+                    (entry.name().to_string(), format!("{:?}", hash))
+                })
+                .collect();
+            let calls = pallet
+                .call_variants()
+                .unwrap_or_default()
+                .iter()
+                .map(|call| {
+                    let hash = pallet.call_hash(&call.name).unwrap_or_default();
+                    (call.name.clone(), format!("{:?}", hash))
+                })
+                .collect();
+            let mut hashes = HashMap::new();
+            let events = pallet
+                .event_variants()
+                .unwrap_or_default()
+                .iter()
+                .map(|event| {
+                    let mut shape_of = |id: u32| {
+                        let hash = type_hash(metadata.types(), id, &mut Vec::new(), &mut hashes);
+                        format!("{:?}", hash)
+                    };
+                    let shape = fields_shape(&event.fields, &mut shape_of);
+                    let hash = H256(blake2_256(shape.as_bytes()));
+                    (event.name.clone(), format!("{:?}", hash))
+                })
+                .collect();
+            Pallet {
+                name: pallet.name().to_string(),
+                items: [("call", calls), ("event", events), ("storage", storage)],
+            }
+        })
+        .collect()
+}
+
+fn diff_items(kind: &str, old: &Items, new: &Items, lines: &mut Vec<String>) {
+    for (name, fingerprint) in new {
+        match old.get(name) {
+            None => lines.push(format!("+ {} {}", kind, name)),
+            Some(old_fingerprint) if old_fingerprint != fingerprint => {
+                lines.push(format!("~ {} {}", kind, name))
+            }
+            Some(_) => {}
+        }
+    }
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        lines.push(format!("- {} {}", kind, name));
+    }
+}
+
+/// Prints the pallets, calls, events and storage entries added (`+`), removed (`-`) or changed
+/// (`~`) between two metadata versions, and the monitor checks decoding the changed pallets.
+fn print_changelog(old: &Metadata, new: &Metadata) {
+    let old_pallets = pallets(old);
+    let new_pallets = pallets(new);
+    let mut changed = BTreeSet::new();
+
+    for pallet in &new_pallets {
+        let Some(old_pallet) = old_pallets.iter().find(|old| old.name == pallet.name) else {
+            let counts: Vec<String> = pallet
+                .items
+                .iter()
+                .map(|(kind, items)| format!("{} {}", items.len(), kind))
+                .collect();
+            println!("+ pallet {} ({})", pallet.name, counts.join(", "));
+            changed.insert(pallet.name.as_str());
+            continue;
+        };
+
+        let mut lines = Vec::new();
+        for ((kind, old_items), (_, new_items)) in old_pallet.items.iter().zip(&pallet.items) {
+            diff_items(kind, old_items, new_items, &mut lines);
+        }
+        if !lines.is_empty() {
+            println!("~ pallet {}", pallet.name);
+            for line in lines {
+                println!("    {}", line);
+            }
+            changed.insert(pallet.name.as_str());
+        }
+    }
+    for pallet in &old_pallets {
+        if !new_pallets.iter().any(|new| new.name == pallet.name) {
+            println!("- pallet {}", pallet.name);
+            changed.insert(pallet.name.as_str());
+        }
+    }
+
+    if changed.is_empty() {
+        println!("No pallet, call, event or storage changes");
+        return;
+    }
+    let affected: Vec<&str> = CHECK_PALLETS
+        .iter()
+        .filter(|(_, pallets)| pallets.iter().any(|pallet| changed.contains(pallet)))
+        .map(|(check, _)| *check)
+        .collect();
+    if affected.is_empty() {
+        println!("\nNo monitor checks decode the changed pallets");
+    } else {
+        println!(
+            "\nMonitor checks that may be affected: {}",
+            affected.join(", ")
+        );
+    }
+}
+
+/// Encoded V15 metadata at a block, or at the best block. `state_getMetadata` only serves V14,
+/// so it is requested from the runtime API.
+pub async fn fetch_v15(rpc: &LegacyRpcMethods<AvailConfig>, at: Option<H256>) -> Result<Vec<u8>> {
+    let response = rpc
+        .state_call(
+            "Metadata_metadata_at_version",
+            Some(&METADATA_VERSION.encode()),
+            at,
+        )
+        .await?;
+    Option::<Vec<u8>>::decode(&mut &response[..])
+        .context("Invalid metadata_at_version response from the node")?
+        .ok_or_else(|| anyhow!("The node does not serve V{} metadata", METADATA_VERSION))
+}
+
+/// Downloads the V15 metadata of the node's best block into `path` and prints what changed
+/// since the metadata already there. Versions are compared by their metadata hash, so an
+/// artifact that only differs in encoding is left alone.
+pub async fn update_metadata(path: &Path, dry_run: bool) -> Result<()> {
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client);
+    let bytes = fetch_v15(&rpc, None).await?;
+    let new = Metadata::decode(&mut &bytes[..]).context("Failed to decode the node's metadata")?;
+
+    match fs::read(path) {
+        Ok(old_bytes) => {
+            let old = Metadata::decode(&mut &old_bytes[..])
+                .with_context(|| format!("Failed to decode {}", path.display()))?;
+            if old.hasher().hash() == new.hasher().hash() {
+                println!("{} is up to date", path.display());
+                return Ok(());
+            }
+            println!("Changes since {}:\n", path.display());
+            print_changelog(&old, &new);
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("{} does not exist yet", path.display());
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+    fs::write(path, &bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!(
+        "\nWrote {} bytes to {}. Regenerate src/avail_api.rs with `subxt codegen --file {}` and \
+         rebuild.",
+        bytes.len(),
+        path.display(),
+        path.display()
+    );
+
+    Ok(())
+}
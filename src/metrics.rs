@@ -0,0 +1,97 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+};
+
+/// Metrics exported in the Prometheus text format on `/metrics`.
+static REGISTRY: OnceLock<Mutex<BTreeMap<&'static str, Family>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+struct Family {
+    kind: MetricKind,
+    help: &'static str,
+    /// Value of every label combination, labels sorted by name.
+    series: BTreeMap<Vec<(String, String)>, f64>,
+}
+
+fn update(
+    kind: MetricKind,
+    name: &'static str,
+    help: &'static str,
+    labels: &[(&str, &str)],
+    apply: impl FnOnce(&mut f64),
+) {
+    let mut labels: Vec<(String, String)> = labels
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    labels.sort();
+
+    let mut registry = REGISTRY
+        .get_or_init(Default::default)
+        .lock()
+        .expect("metrics lock poisoned");
+    let family = registry.entry(name).or_insert_with(|| Family {
+        kind,
+        help,
+        series: BTreeMap::new(),
+    });
+    apply(family.series.entry(labels).or_insert(0.0));
+}
+
+/// Increases a counter by `by`.
+pub fn inc_counter(name: &'static str, help: &'static str, labels: &[(&str, &str)], by: f64) {
+    update(MetricKind::Counter, name, help, labels, |value| {
+        *value += by
+    });
+}
+
+/// Sets a gauge to `value`.
+pub fn set_gauge(name: &'static str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+    update(MetricKind::Gauge, name, help, labels, |current| {
+        *current = value
+    });
+}
+
+/// Renders every metric in the Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = REGISTRY
+        .get_or_init(Default::default)
+        .lock()
+        .expect("metrics lock poisoned");
+    let mut out = String::new();
+    for (name, family) in registry.iter() {
+        let kind = match family.kind {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        };
+        let _ = writeln!(out, "# HELP {} {}", name, family.help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (labels, value) in &family.series {
+            if labels.is_empty() {
+                let _ = writeln!(out, "{} {}", name, value);
+                continue;
+            }
+            let labels = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,274 @@
+use crate::{
+    alert::AlertManager,
+    app_keys::check_app_keys,
+    da_stats::check_da_submissions,
+    decode,
+    epoch_blocks::check_block_production,
+    era_report::check_era_report,
+    finality::FinalityMonitor,
+    fullness::FullnessMonitor,
+    im_online::HeartbeatMonitor,
+    liveness::LivenessMonitor,
+    notifier::{Notifiers, SinkConfig},
+    offences::check_offences,
+    privileged::check_privileged,
+    queue::{NotificationQueue, RetryPolicy},
+    runtime::RuntimeMonitor,
+    utils::{api, AvailConfig, MonitorOpts, Opts},
+    validator_set::check_validator_set,
+    vector::VectorMonitor,
+    watchlist::Watchlist,
+};
+use anyhow::Result;
+use log::error;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient, StreamOfResults},
+    blocks::Block,
+    client::OnlineClient,
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+
+/// How often time based checks run, independent of block arrival.
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+type ChainBlock = Block<AvailConfig, OnlineClient<AvailConfig>>;
+
+/// State shared by the chain monitor checks.
+struct Monitor {
+    client: OnlineClient<AvailConfig>,
+    rpc_client: RpcClient,
+    alerts: AlertManager,
+    session_per_era: u32,
+    slash_defer_duration: u32,
+    heartbeats: HeartbeatMonitor,
+    finality: FinalityMonitor,
+    liveness: LivenessMonitor,
+    fullness: FullnessMonitor,
+    runtime: RuntimeMonitor,
+    vector: VectorMonitor,
+    watchlist: Watchlist,
+}
+
+impl Monitor {
+    async fn on_finalized(&mut self, block: ChainBlock, seen_at: Instant) -> Result<()> {
+        let events = block.events().await?;
+        let (hash, number) = (block.hash(), block.number());
+
+        if let Err(e) = self
+            .runtime
+            .on_finalized(&self.client, &block, &mut self.alerts)
+            .await
+        {
+            error!("Runtime upgrade check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = self
+            .finality
+            .on_finalized(
+                &self.client,
+                hash,
+                number,
+                seen_at,
+                &events,
+                &mut self.alerts,
+            )
+            .await
+        {
+            error!("Finality check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = self
+            .heartbeats
+            .on_block(&self.client, hash, number, &events, &mut self.alerts)
+            .await
+        {
+            error!("ImOnline check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = check_offences(
+            &self.client,
+            hash,
+            number,
+            &events,
+            self.slash_defer_duration,
+            &mut self.alerts,
+        )
+        .await
+        {
+            error!("Offence check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = check_block_production(
+            &self.client,
+            &self.rpc_client,
+            &block,
+            &events,
+            self.session_per_era,
+            &mut self.alerts,
+        )
+        .await
+        {
+            error!(
+                "Block production check failed at block #{}: {:?}",
+                number, e
+            );
+        }
+        if let Err(e) =
+            check_era_report(&self.client, hash, number, &events, &mut self.alerts).await
+        {
+            error!("Era report failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) =
+            check_validator_set(&self.client, hash, number, &events, &mut self.alerts).await
+        {
+            error!("Validator set check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = self
+            .watchlist
+            .on_block(&self.client, &block, &events, &mut self.alerts)
+            .await
+        {
+            error!("Watchlist check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = check_da_submissions(&block, &events, &mut self.alerts).await {
+            error!("DA submissions check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = self
+            .fullness
+            .on_finalized(block.header(), &events, &mut self.alerts)
+        {
+            error!("Fullness check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = check_app_keys(&self.client, hash, number, &events, &mut self.alerts).await
+        {
+            error!("App key check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = check_privileged(&self.client, &block, &events, &mut self.alerts).await {
+            error!("Privileged call check failed at block #{}: {:?}", number, e);
+        }
+        if let Err(e) = self
+            .vector
+            .on_block(&self.client, &block, &events, &mut self.alerts)
+            .await
+        {
+            error!("Vector check failed at block #{}: {:?}", number, e);
+        }
+
+        Ok(())
+    }
+
+    fn on_best(&mut self, block: ChainBlock, seen_at: Instant) {
+        self.finality.on_best(block.number());
+        self.liveness.on_best(block.header(), seen_at);
+    }
+
+    fn on_tick(&mut self) {
+        self.finality.check(&mut self.alerts);
+        self.liveness.check(&mut self.alerts);
+    }
+}
+
+/// Forwards a block subscription from its own task, with the time each block arrived, so the
+/// time based checks are not thrown off by blocks that wait while a finalized block is checked.
+fn forward_blocks(
+    mut sub: StreamOfResults<ChainBlock>,
+) -> mpsc::UnboundedReceiver<(Result<ChainBlock, subxt::Error>, Instant)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(block) = sub.next().await {
+            if tx.send((block, Instant::now())).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Monitors the chain and alerts on block production, finality, validator and slashing issues
+pub async fn monitor_chain(opts: MonitorOpts) -> Result<()> {
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client.clone()).await?;
+
+    let mut sinks = opts.notify;
+    sinks.extend(opts.channel_id.map(SinkConfig::slack));
+    let mut notifiers = Notifiers::from_configs(&sinks, opts.explorer_url.as_deref())?;
+    let watchlist = Watchlist::load(&opts.watch, opts.watch_file.as_deref())?;
+    watchlist.add_routes(&mut notifiers, opts.explorer_url.as_deref())?;
+    let retry_policy = RetryPolicy {
+        max_attempts: opts.notify_attempts,
+        initial_backoff: Duration::from_secs(2),
+        max_backoff: Duration::from_secs(300),
+    };
+    let queue = NotificationQueue::start(notifiers, retry_policy, opts.dead_letter_file);
+    let chain = rpc.system_chain().await?;
+    let mut alerts = AlertManager::new(queue, chain, Duration::from_secs(opts.dedup_window));
+
+    // Keep the client's metadata current across runtime upgrades, and report early if the
+    // bundled metadata is already out of date
+    let updater = client.updater();
+    tokio::spawn(async move {
+        if let Err(e) = updater.perform_runtime_updates().await {
+            error!("Runtime update subscription failed: {:?}", e);
+        }
+    });
+    let runtime = RuntimeMonitor::new(rpc_client.clone());
+    runtime.check_metadata(None, &mut alerts).await?;
+
+    let constant_query = api::constants().staking().sessions_per_era();
+    let session_per_era = decode::constant(&client, &constant_query)?;
+    let slash_defer_duration =
+        decode::constant(&client, &api::constants().staking().slash_defer_duration())?;
+    let heartbeats = HeartbeatMonitor::new(&client, opts.heartbeat_threshold)?;
+    let finality = FinalityMonitor::new(
+        opts.finality_lag_blocks,
+        Duration::from_secs(opts.finality_lag_secs),
+    );
+    let liveness = LivenessMonitor::new(
+        &client,
+        opts.stall_slots,
+        opts.rate_window_slots,
+        opts.min_production_rate,
+    )?;
+    let fullness = FullnessMonitor::new(&client, opts.fullness_window, opts.fullness_threshold)?;
+    let vector = VectorMonitor::new(Duration::from_secs(opts.vector_head_stall_secs));
+
+    let mut monitor = Monitor {
+        client: client.clone(),
+        rpc_client,
+        alerts,
+        session_per_era,
+        slash_defer_duration,
+        heartbeats,
+        finality,
+        liveness,
+        fullness,
+        runtime,
+        vector,
+        watchlist,
+    };
+
+    // Subscribe to finalized blocks for the checks, and best blocks for finality lag and liveness
+    let mut finalized_sub = forward_blocks(client.blocks().subscribe_finalized().await?);
+    let mut best_sub = forward_blocks(client.blocks().subscribe_best().await?);
+    // Ticks missed during a slow finalized block are dropped rather than fired back-to-back
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        // Best blocks that arrived meanwhile are applied before a tick checks for a stall
+        tokio::select! {
+            biased;
+            block = best_sub.recv() => match block {
+                Some((block, seen_at)) => monitor.on_best(block?, seen_at),
+                None => break,
+            },
+            _ = ticker.tick() => monitor.on_tick(),
+            block = finalized_sub.recv() => match block {
+                Some((block, seen_at)) => monitor.on_finalized(block?, seen_at).await?,
+                None => break,
+            },
+        }
+    }
+
+    Ok(())
+}
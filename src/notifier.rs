@@ -0,0 +1,850 @@
+use crate::alert::{Alert, AlertStatus, Severity};
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::BoxFuture;
+use reqwest::{header::RETRY_AFTER, Client, RequestBuilder, Response, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const DEFAULT_SLACK_API_URL: &str = "https://slack.com/api";
+const DEFAULT_TELEGRAM_API_URL: &str = "https://api.telegram.org";
+const DEFAULT_PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// A destination that chain monitor messages can be delivered to.
+pub trait Notifier: Send + Sync {
+    /// Short name of the sink, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Renders and delivers a single alert to the sink.
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Kinds of notification sinks supported by `--notify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    Slack,
+    SlackWebhook,
+    Discord,
+    Telegram,
+    Matrix,
+    Webhook,
+    PagerDuty,
+}
+
+impl FromStr for SinkKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "slack" => Ok(SinkKind::Slack),
+            "slack-webhook" => Ok(SinkKind::SlackWebhook),
+            "discord" => Ok(SinkKind::Discord),
+            "telegram" => Ok(SinkKind::Telegram),
+            "matrix" => Ok(SinkKind::Matrix),
+            "webhook" => Ok(SinkKind::Webhook),
+            "pagerduty" => Ok(SinkKind::PagerDuty),
+            other => bail!("Unknown notification sink: {}", other),
+        }
+    }
+}
+
+/// Sink specification parsed from `--notify <kind>[@<min-severity>]=<target>`.
+///
+/// The target is the Slack channel ID for `slack`, the Telegram chat ID for `telegram`,
+/// the room ID for `matrix`, the routing key for `pagerduty` and the URL for webhooks.
+#[derive(Debug, Clone)]
+pub struct SinkConfig {
+    pub kind: SinkKind,
+    pub min_severity: Severity,
+    pub target: String,
+}
+
+impl SinkConfig {
+    /// Slack bot sink for the legacy `--channel-id` option.
+    pub fn slack(channel_id: String) -> Self {
+        Self {
+            kind: SinkKind::Slack,
+            min_severity: Severity::Info,
+            target: channel_id,
+        }
+    }
+
+    /// Builds the notifier described by this config, reading credentials from the environment.
+    pub fn build(&self, client: Client, explorer_url: Option<&str>) -> Result<Box<dyn Notifier>> {
+        let target = self.target.clone();
+        let notifier: Box<dyn Notifier> = match self.kind {
+            SinkKind::Slack => Box::new(SlackNotifier {
+                client,
+                api_url: env_or("SLACK_API_URL", DEFAULT_SLACK_API_URL),
+                token: env::var("SLACK_TOKEN").context("SLACK_TOKEN must be set for slack")?,
+                channel_id: target,
+                explorer_url: explorer_url.map(|url| url.trim_end_matches('/').to_string()),
+                threads: Mutex::new(BTreeMap::new()),
+            }),
+            SinkKind::SlackWebhook => Box::new(SlackWebhookNotifier {
+                client,
+                url: target,
+            }),
+            SinkKind::Discord => Box::new(DiscordNotifier {
+                client,
+                url: target,
+            }),
+            SinkKind::Telegram => Box::new(TelegramNotifier {
+                client,
+                api_url: env_or("TELEGRAM_API_URL", DEFAULT_TELEGRAM_API_URL),
+                token: env::var("TELEGRAM_BOT_TOKEN")
+                    .context("TELEGRAM_BOT_TOKEN must be set for telegram")?,
+                chat_id: target,
+            }),
+            SinkKind::Matrix => Box::new(MatrixNotifier {
+                client,
+                homeserver: env::var("MATRIX_HOMESERVER")
+                    .context("MATRIX_HOMESERVER must be set for matrix")?,
+                access_token: env::var("MATRIX_ACCESS_TOKEN")
+                    .context("MATRIX_ACCESS_TOKEN must be set for matrix")?,
+                room_id: target,
+                txn_counter: AtomicU64::new(0),
+            }),
+            SinkKind::Webhook => Box::new(WebhookNotifier {
+                client,
+                url: target,
+            }),
+            SinkKind::PagerDuty => Box::new(PagerDutyNotifier {
+                client,
+                events_url: env_or("PAGERDUTY_EVENTS_URL", DEFAULT_PAGERDUTY_EVENTS_URL),
+                routing_key: target,
+            }),
+        };
+        Ok(notifier)
+    }
+}
+
+impl FromStr for SinkConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (spec, target) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected <kind>[@<severity>]=<target>, got: {}", s))?;
+        let (kind, min_severity) = match spec.split_once('@') {
+            Some((kind, severity)) => (kind.parse()?, severity.parse()?),
+            None => (spec.parse()?, Severity::Info),
+        };
+        if target.is_empty() {
+            bail!("Missing target for notification sink: {}", spec);
+        }
+        Ok(Self {
+            kind,
+            min_severity,
+            target: target.to_string(),
+        })
+    }
+}
+
+pub(crate) struct Sink {
+    pub(crate) min_severity: Severity,
+    /// Route of the alerts delivered to this sink, `None` for the default sinks.
+    pub(crate) route: Option<String>,
+    pub(crate) notifier: Box<dyn Notifier>,
+}
+
+/// Set of notification sinks, each with its own minimum severity.
+#[derive(Default)]
+pub struct Notifiers {
+    client: Client,
+    pub(crate) sinks: Vec<Sink>,
+}
+
+impl Notifiers {
+    pub fn from_configs(configs: &[SinkConfig], explorer_url: Option<&str>) -> Result<Self> {
+        let mut notifiers = Self::default();
+        notifiers.add_configs(None, configs, explorer_url)?;
+        Ok(notifiers)
+    }
+
+    /// Adds sinks that receive the alerts routed to `route` instead of the default sinks.
+    pub fn add_route(
+        &mut self,
+        route: &str,
+        configs: &[SinkConfig],
+        explorer_url: Option<&str>,
+    ) -> Result<()> {
+        self.add_configs(Some(route.to_string()), configs, explorer_url)
+    }
+
+    fn add_configs(
+        &mut self,
+        route: Option<String>,
+        configs: &[SinkConfig],
+        explorer_url: Option<&str>,
+    ) -> Result<()> {
+        for config in configs {
+            let notifier = config.build(self.client.clone(), explorer_url)?;
+            self.sinks.push(Sink {
+                min_severity: config.min_severity,
+                route: route.clone(),
+                notifier,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when a sink asks us to back off, e.g. via a `Retry-After` header.
+#[derive(Debug)]
+pub struct RetryAfter(pub Duration);
+
+impl fmt::Display for RetryAfter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rate limited, retry after {:?}", self.0)
+    }
+}
+
+impl std::error::Error for RetryAfter {}
+
+fn env_or(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_string())
+}
+
+/// Sends the request and turns a non-success HTTP status into an error.
+/// Rate limited responses carrying `Retry-After` become a [`RetryAfter`] error.
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        if let Some(secs) = retry_after {
+            return Err(RetryAfter(Duration::from_secs(secs)).into());
+        }
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Status: {:?}, Body: {}", status, body);
+    }
+    Ok(response)
+}
+
+/// Number of eras for which Slack thread timestamps are remembered.
+const SLACK_THREADS_KEPT: usize = 8;
+/// Slack limits a header block to 150 characters and a section to 3000.
+const SLACK_HEADER_LIMIT: usize = 150;
+const SLACK_SECTION_LIMIT: usize = 3000;
+
+/// Response of the Slack Web API. Errors are reported with HTTP 200 and `ok: false`.
+#[derive(Debug, Deserialize)]
+struct SlackResponse {
+    ok: bool,
+    error: Option<String>,
+    ts: Option<String>,
+}
+
+/// Slack Web API `chat.postMessage` using a bot token. Messages are sent as Block Kit and
+/// alerts for the same era are grouped in a thread started by the first one.
+pub struct SlackNotifier {
+    client: Client,
+    api_url: String,
+    token: String,
+    channel_id: String,
+    explorer_url: Option<String>,
+    /// Era index to the `ts` of the message that started its thread.
+    threads: Mutex<BTreeMap<u32, String>>,
+}
+
+impl SlackNotifier {
+    fn blocks(&self, alert: &Alert) -> Vec<Value> {
+        let header = format!("{} {}: {}", alert.status_label(), alert.chain, alert.title);
+        let mut blocks = vec![json!({
+            "type": "header",
+            "text": { "type": "plain_text", "text": truncate(&header, SLACK_HEADER_LIMIT) },
+        })];
+        if !alert.message.is_empty() {
+            blocks.push(json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": truncate(&alert.message, SLACK_SECTION_LIMIT) },
+            }));
+        }
+        // Slack allows at most 10 fields per section
+        for chunk in alert.fields.chunks(10) {
+            let fields: Vec<Value> = chunk
+                .iter()
+                .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) }))
+                .collect();
+            blocks.push(json!({ "type": "section", "fields": fields }));
+        }
+        if !alert.block_links.is_empty() {
+            let links: Vec<String> = alert
+                .block_links
+                .iter()
+                .map(|(label, block)| match &self.explorer_url {
+                    Some(url) => format!("<{}/{}|{} #{}>", url, block, label, block),
+                    None => format!("{} #{}", label, block),
+                })
+                .collect();
+            blocks.push(json!({
+                "type": "context",
+                "elements": [{ "type": "mrkdwn", "text": links.join(" | ") }],
+            }));
+        }
+        blocks
+    }
+
+    fn thread_ts(&self, era: Option<u32>) -> Option<String> {
+        let threads = self.threads.lock().expect("slack threads lock poisoned");
+        era.and_then(|era| threads.get(&era).cloned())
+    }
+
+    fn remember_thread(&self, era: u32, ts: String) {
+        let mut threads = self.threads.lock().expect("slack threads lock poisoned");
+        threads.entry(era).or_insert(ts);
+        while threads.len() > SLACK_THREADS_KEPT {
+            threads.pop_first();
+        }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let era = alert.context.era;
+            let thread_ts = self.thread_ts(era);
+            let mut payload = json!({
+                "channel": self.channel_id,
+                "text": alert.render_text(),
+                "blocks": self.blocks(alert),
+            });
+            if let Some(ts) = &thread_ts {
+                payload["thread_ts"] = json!(ts);
+            }
+            let request = self
+                .client
+                .post(format!("{}/chat.postMessage", self.api_url))
+                .bearer_auth(&self.token)
+                .json(&payload);
+            let response: SlackResponse = send(request).await?.json().await?;
+            if !response.ok {
+                bail!(
+                    "Slack API error: {}",
+                    response.error.unwrap_or_else(|| "unknown".to_string())
+                );
+            }
+            if let (Some(era), None, Some(ts)) = (era, thread_ts, response.ts) {
+                self.remember_thread(era, ts);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Truncates `s` to at most `max` characters.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut truncated: String = s.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Slack incoming webhook.
+pub struct SlackWebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl Notifier for SlackWebhookNotifier {
+    fn name(&self) -> &'static str {
+        "slack-webhook"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = alert.render_text();
+            send(
+                self.client
+                    .post(&self.url)
+                    .json(&json!({ "text": message })),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Discord channel webhook.
+pub struct DiscordNotifier {
+    client: Client,
+    url: String,
+}
+
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = alert.render_text();
+            send(
+                self.client
+                    .post(&self.url)
+                    .json(&json!({ "content": message })),
+            )
+            .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Telegram Bot API `sendMessage`.
+pub struct TelegramNotifier {
+    client: Client,
+    api_url: String,
+    token: String,
+    chat_id: String,
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = alert.render_text();
+            let payload = json!({
+                "chat_id": self.chat_id,
+                "text": message,
+            });
+            let url = format!("{}/bot{}/sendMessage", self.api_url, self.token);
+            send(self.client.post(url).json(&payload)).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Matrix client-server API `m.room.message` event.
+pub struct MatrixNotifier {
+    client: Client,
+    homeserver: String,
+    access_token: String,
+    room_id: String,
+    txn_counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    fn next_txn_id(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let counter = self.txn_counter.fetch_add(1, Ordering::Relaxed);
+        format!("avail-monitor-{}-{}", now, counter)
+    }
+}
+
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = alert.render_text();
+            let txn_id = self.next_txn_id();
+            let mut url = Url::parse(&self.homeserver)?;
+            url.path_segments_mut()
+                .map_err(|_| anyhow!("Invalid Matrix homeserver URL: {}", self.homeserver))?
+                .pop_if_empty()
+                .extend(&[
+                    "_matrix",
+                    "client",
+                    "v3",
+                    "rooms",
+                    self.room_id.as_str(),
+                    "send",
+                    "m.room.message",
+                    txn_id.as_str(),
+                ]);
+            let payload = json!({
+                "msgtype": "m.text",
+                "body": message,
+            });
+            let request = self
+                .client
+                .put(url)
+                .bearer_auth(&self.access_token)
+                .json(&payload);
+            send(request).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Generic JSON webhook receiving the serialized [`Alert`].
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            send(self.client.post(&self.url).json(alert)).await?;
+            Ok(())
+        })
+    }
+}
+
+/// PagerDuty Events API v2. Resolved alerts resolve the incident with the same dedup key.
+pub struct PagerDutyNotifier {
+    client: Client,
+    events_url: String,
+    routing_key: String,
+}
+
+impl Notifier for PagerDutyNotifier {
+    fn name(&self) -> &'static str {
+        "pagerduty"
+    }
+
+    fn notify<'a>(&'a self, alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let event_action = match alert.status {
+                AlertStatus::Firing => "trigger",
+                AlertStatus::Resolved => "resolve",
+            };
+            let payload = json!({
+                "routing_key": self.routing_key,
+                "event_action": event_action,
+                "dedup_key": format!("{}:{}", alert.chain, alert.dedup_key),
+                "payload": {
+                    "summary": format!("{}: {}", alert.chain, alert.title),
+                    "source": "avail-monitor",
+                    "severity": alert.severity.to_string(),
+                    "custom_details": alert,
+                },
+            });
+            send(self.client.post(&self.events_url).json(&payload)).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertKind;
+    use std::{net::SocketAddr, sync::Arc};
+    use warp::{http::Response as HttpResponse, hyper::body::Bytes, path::FullPath, Filter};
+
+    /// Request received by the stub server.
+    #[derive(Debug)]
+    struct Recorded {
+        method: String,
+        path: String,
+        authorization: Option<String>,
+        body: Value,
+    }
+
+    /// Local HTTP server answering every request with the same canned response.
+    struct Stub {
+        url: String,
+        requests: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    impl Stub {
+        async fn start(status: u16, headers: &[(&'static str, &'static str)], body: &str) -> Self {
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            let headers = headers.to_vec();
+            let body = body.to_string();
+            let routes = warp::method()
+                .and(warp::path::full())
+                .and(warp::header::optional::<String>("authorization"))
+                .and(warp::body::bytes())
+                .map(
+                    move |method: warp::http::Method,
+                          path: FullPath,
+                          authorization: Option<String>,
+                          request: Bytes| {
+                        recorded.lock().unwrap().push(Recorded {
+                            method: method.to_string(),
+                            path: path.as_str().to_string(),
+                            authorization,
+                            body: serde_json::from_slice(&request).unwrap_or(Value::Null),
+                        });
+                        let mut response = HttpResponse::builder().status(status);
+                        for (name, value) in &headers {
+                            response = response.header(*name, *value);
+                        }
+                        response.body(body.clone()).unwrap()
+                    },
+                );
+            let (addr, server): (SocketAddr, _) =
+                warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+            Self {
+                url: format!("http://{}", addr),
+                requests,
+            }
+        }
+
+        async fn ok() -> Self {
+            Self::start(200, &[], "{}").await
+        }
+
+        fn requests(&self) -> std::sync::MutexGuard<'_, Vec<Recorded>> {
+            self.requests.lock().unwrap()
+        }
+    }
+
+    fn alert() -> Alert {
+        let mut alert = Alert::new(AlertKind::Offence, Severity::Critical, "Offence reported")
+            .message("validator equivocated")
+            .era(42)
+            .field("Validator", "alice")
+            .block_link("Block", 100)
+            .dedup_key("offence:100");
+        alert.chain = "turing".to_string();
+        alert
+    }
+
+    fn slack(stub: &Stub) -> SlackNotifier {
+        SlackNotifier {
+            client: Client::new(),
+            api_url: stub.url.clone(),
+            token: "xoxb-test".to_string(),
+            channel_id: "C123".to_string(),
+            explorer_url: Some("https://explorer.test/block".to_string()),
+            threads: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn slack_posts_blocks_and_threads_by_era() {
+        let stub = Stub::start(200, &[], r#"{"ok":true,"ts":"1700000000.000100"}"#).await;
+        let notifier = slack(&stub);
+        notifier.notify(&alert()).await.unwrap();
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 2);
+        let first = &requests[0];
+        assert_eq!(first.method, "POST");
+        assert_eq!(first.path, "/chat.postMessage");
+        assert_eq!(first.authorization.as_deref(), Some("Bearer xoxb-test"));
+        assert_eq!(first.body["channel"], "C123");
+        assert_eq!(first.body["text"], alert().render_text());
+        assert_eq!(
+            first.body["blocks"][0]["text"]["text"],
+            "CRITICAL turing: Offence reported"
+        );
+        assert!(first.body.get("thread_ts").is_none());
+        assert_eq!(
+            first.body["blocks"][3]["elements"][0]["text"],
+            "<https://explorer.test/block/100|Block #100>"
+        );
+        assert_eq!(requests[1].body["thread_ts"], "1700000000.000100");
+    }
+
+    #[tokio::test]
+    async fn slack_reports_api_errors() {
+        let stub = Stub::start(200, &[], r#"{"ok":false,"error":"channel_not_found"}"#).await;
+        let error = slack(&stub).notify(&alert()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Slack API error: channel_not_found");
+    }
+
+    #[tokio::test]
+    async fn rate_limited_responses_become_retry_after() {
+        let stub = Stub::start(429, &[("retry-after", "7")], "slow down").await;
+        let error = slack(&stub).notify(&alert()).await.unwrap_err();
+        let retry_after = error
+            .downcast_ref::<RetryAfter>()
+            .expect("RetryAfter error");
+        assert_eq!(retry_after.0, Duration::from_secs(7));
+    }
+
+    #[tokio::test]
+    async fn non_success_status_is_an_error() {
+        let stub = Stub::start(500, &[], "internal error").await;
+        let notifier = WebhookNotifier {
+            client: Client::new(),
+            url: format!("{}/hook", stub.url),
+        };
+        let error = notifier.notify(&alert()).await.unwrap_err();
+        assert!(error.downcast_ref::<RetryAfter>().is_none());
+        assert!(error.to_string().contains("500"));
+        assert!(error.to_string().contains("internal error"));
+
+        // Without Retry-After a 429 is reported like any other failure.
+        let stub = Stub::start(429, &[], "").await;
+        let notifier = DiscordNotifier {
+            client: Client::new(),
+            url: stub.url.clone(),
+        };
+        let error = notifier.notify(&alert()).await.unwrap_err();
+        assert!(error.downcast_ref::<RetryAfter>().is_none());
+    }
+
+    #[tokio::test]
+    async fn slack_webhook_posts_text() {
+        let stub = Stub::ok().await;
+        let notifier = SlackWebhookNotifier {
+            client: Client::new(),
+            url: format!("{}/services/T0/B0/x", stub.url),
+        };
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/services/T0/B0/x");
+        assert_eq!(requests[0].body, json!({ "text": alert().render_text() }));
+    }
+
+    #[tokio::test]
+    async fn discord_posts_content() {
+        let stub = Stub::ok().await;
+        let notifier = DiscordNotifier {
+            client: Client::new(),
+            url: format!("{}/api/webhooks/1/token", stub.url),
+        };
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].path, "/api/webhooks/1/token");
+        assert_eq!(
+            requests[0].body,
+            json!({ "content": alert().render_text() })
+        );
+    }
+
+    #[tokio::test]
+    async fn telegram_sends_message_to_chat() {
+        let stub = Stub::ok().await;
+        let notifier = TelegramNotifier {
+            client: Client::new(),
+            api_url: stub.url.clone(),
+            token: "123:abc".to_string(),
+            chat_id: "-100".to_string(),
+        };
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].path, "/bot123:abc/sendMessage");
+        assert_eq!(
+            requests[0].body,
+            json!({ "chat_id": "-100", "text": alert().render_text() })
+        );
+    }
+
+    #[tokio::test]
+    async fn matrix_puts_room_message() {
+        let stub = Stub::ok().await;
+        let notifier = MatrixNotifier {
+            client: Client::new(),
+            homeserver: format!("{}/", stub.url),
+            access_token: "syt_token".to_string(),
+            room_id: "!room:example.org".to_string(),
+            txn_counter: AtomicU64::new(0),
+        };
+        notifier.notify(&alert()).await.unwrap();
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        let request = &requests[0];
+        assert_eq!(request.method, "PUT");
+        assert!(request.path.starts_with(
+            "/_matrix/client/v3/rooms/!room:example.org/send/m.room.message/avail-monitor-"
+        ));
+        assert_ne!(request.path, requests[1].path);
+        assert_eq!(request.authorization.as_deref(), Some("Bearer syt_token"));
+        assert_eq!(
+            request.body,
+            json!({ "msgtype": "m.text", "body": alert().render_text() })
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_serialized_alert() {
+        let stub = Stub::ok().await;
+        let notifier = WebhookNotifier {
+            client: Client::new(),
+            url: format!("{}/alerts", stub.url),
+        };
+        notifier.notify(&alert()).await.unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests[0].path, "/alerts");
+        assert_eq!(requests[0].body, serde_json::to_value(alert()).unwrap());
+        assert_eq!(requests[0].body["kind"], "offence");
+        assert_eq!(requests[0].body["status"], "firing");
+    }
+
+    #[tokio::test]
+    async fn pagerduty_triggers_and_resolves_by_dedup_key() {
+        let stub = Stub::start(202, &[], r#"{"status":"success"}"#).await;
+        let notifier = PagerDutyNotifier {
+            client: Client::new(),
+            events_url: format!("{}/v2/enqueue", stub.url),
+            routing_key: "R0UT1NG".to_string(),
+        };
+        let mut resolved = alert();
+        resolved.status = AlertStatus::Resolved;
+        notifier.notify(&alert()).await.unwrap();
+        notifier.notify(&resolved).await.unwrap();
+
+        let requests = stub.requests();
+        let trigger = &requests[0].body;
+        assert_eq!(requests[0].path, "/v2/enqueue");
+        assert_eq!(trigger["routing_key"], "R0UT1NG");
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["dedup_key"], "turing:offence:100");
+        assert_eq!(trigger["payload"]["summary"], "turing: Offence reported");
+        assert_eq!(trigger["payload"]["severity"], "critical");
+        assert_eq!(requests[1].body["event_action"], "resolve");
+        assert_eq!(requests[1].body["dedup_key"], "turing:offence:100");
+    }
+
+    #[tokio::test]
+    async fn build_uses_api_url_overrides() {
+        let stub = Stub::start(200, &[], r#"{"ok":true}"#).await;
+        env::set_var("SLACK_API_URL", &stub.url);
+        env::set_var("SLACK_TOKEN", "xoxb-env");
+        env::set_var("TELEGRAM_API_URL", &stub.url);
+        env::set_var("TELEGRAM_BOT_TOKEN", "456:def");
+        env::set_var("PAGERDUTY_EVENTS_URL", format!("{}/enqueue", stub.url));
+
+        for spec in ["slack=C1", "telegram=42", "pagerduty=KEY"] {
+            let config: SinkConfig = spec.parse().unwrap();
+            let notifier = config.build(Client::new(), None).unwrap();
+            notifier.notify(&alert()).await.unwrap();
+        }
+
+        let paths: Vec<String> = stub.requests().iter().map(|r| r.path.clone()).collect();
+        assert_eq!(
+            paths,
+            ["/chat.postMessage", "/bot456:def/sendMessage", "/enqueue"]
+        );
+    }
+}
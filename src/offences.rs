@@ -0,0 +1,233 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode::{self, StorageAt},
+    identity::{resolve, resolve_all},
+    utils::{
+        api,
+        api::{
+            offences::events::Offence,
+            runtime_types::pallet_staking::UnappliedSlash,
+            staking::events::{OldSlashingReportDiscarded, SlashReported, Slashed},
+        },
+        format_avail, format_perbill, AvailConfig,
+    },
+};
+use anyhow::{anyhow, Result};
+use codec::Decode;
+use futures::StreamExt;
+use sp_core::H256;
+use subxt::{client::OnlineClient, events::Events, utils::AccountId32};
+
+/// Length of the pallet and storage prefix plus the `Twox64Concat` hash in an
+/// `UnappliedSlashes` key.
+const UNAPPLIED_SLASHES_KEY_PREFIX: usize = 32 + 8;
+
+/// Raises alerts for every offence, slash report and applied slash in the block.
+/// `slash_defer_duration` is the `Staking::SlashDeferDuration` constant, in eras.
+pub async fn check_offences(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    block_number: u32,
+    events: &Events<AvailConfig>,
+    slash_defer_duration: u32,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    let storage = decode::storage_at(client, block_hash);
+    let session_index = storage
+        .fetch_or_default(&api::storage().session().current_index())
+        .await?;
+    let active_era = storage
+        .fetch(&api::storage().staking().active_era())
+        .await?
+        .map(|era| era.index);
+
+    for offence in decode::find::<Offence>(events.iter()) {
+        let offence = offence?;
+        let kind = offence_kind(&offence.kind);
+        let timeslot = describe_timeslot(&kind, &offence.timeslot);
+
+        let report_ids = storage
+            .fetch_or_default(
+                &api::storage()
+                    .offences()
+                    .concurrent_reports_index(offence.kind, &offence.timeslot[..]),
+            )
+            .await?;
+        let mut offenders = Vec::with_capacity(report_ids.len());
+        for report_id in report_ids {
+            if let Some(report) = storage
+                .fetch(&api::storage().offences().reports(report_id))
+                .await?
+            {
+                offenders.push(report.offender.0);
+            }
+        }
+        let offenders = resolve_all(client, block_hash, &offenders).await;
+
+        let mut alert = Alert::new(
+            AlertKind::Offence,
+            Severity::Warning,
+            format!("Offence reported: {} ({})", kind, timeslot),
+        )
+        .message(format!("Offenders:\n{}", offenders.join("\n")))
+        .field("Kind", kind.clone())
+        .field("Time slot", timeslot)
+        .field("Session", session_index.to_string())
+        .field("Offenders", offenders.len().to_string())
+        .epoch(session_index.into())
+        .block(block_number)
+        .block_link("Reported in", block_number)
+        .dedup_key(format!(
+            "offence:{}:{}",
+            kind,
+            hex_string(&offence.timeslot)
+        ));
+        if let Some(era) = active_era {
+            alert = alert.era(era);
+        }
+        alerts.notify(alert);
+    }
+
+    for report in decode::find::<SlashReported>(events.iter()) {
+        let report = report?;
+        let validator = resolve(client, block_hash, &report.validator).await;
+
+        let mut alert = Alert::new(
+            AlertKind::SlashReported,
+            Severity::Critical,
+            format!("Slash reported for {}", validator),
+        )
+        .field("Validator", validator)
+        .field("Fraction", format_perbill(report.fraction.0))
+        .field("Slash era", report.slash_era.to_string())
+        .field("Session", session_index.to_string())
+        .epoch(session_index.into())
+        .block(block_number)
+        .block_link("Reported in", block_number)
+        .dedup_key(format!(
+            "slash_reported:{}:{}",
+            report.slash_era, report.validator
+        ));
+
+        if let Some((apply_era, pending)) =
+            pending_slash(&storage, &report, slash_defer_duration).await?
+        {
+            alert = alert
+                .field("Deferred until era", apply_era.to_string())
+                .field("Pending slash", format_avail(pending));
+        }
+        if let Some(era) = active_era {
+            alert = alert.era(era);
+        }
+        alerts.notify(alert);
+    }
+
+    for slashed in decode::find::<Slashed>(events.iter()) {
+        let slashed = slashed?;
+        let staker = resolve(client, block_hash, &slashed.staker).await;
+        let mut alert = Alert::new(
+            AlertKind::Slashed,
+            Severity::Critical,
+            format!("{} slashed {}", staker, format_avail(slashed.amount)),
+        )
+        .field("Staker", staker)
+        .field("Amount", format_avail(slashed.amount))
+        .field("Session", session_index.to_string())
+        .epoch(session_index.into())
+        .block(block_number)
+        .block_link("Slashed in", block_number)
+        .dedup_key(format!("slashed:{}:{}", block_number, slashed.staker));
+        if let Some(era) = active_era {
+            alert = alert.era(era);
+        }
+        alerts.notify(alert);
+    }
+
+    for discarded in decode::find::<OldSlashingReportDiscarded>(events.iter()) {
+        let discarded = discarded?;
+        let alert = Alert::new(
+            AlertKind::SlashReported,
+            Severity::Info,
+            format!(
+                "Old slashing report for session {} discarded",
+                discarded.session_index
+            ),
+        )
+        .epoch(discarded.session_index.into())
+        .block(block_number)
+        .dedup_key(format!(
+            "old_slashing_report_discarded:{}",
+            discarded.session_index
+        ));
+        alerts.notify(alert);
+    }
+
+    Ok(())
+}
+
+/// Era in which the reported slash is applied and its total amount. The slash is queued for
+/// `slash_era + slash_defer_duration + 1`; if it is not there, all queued eras are searched.
+async fn pending_slash(
+    storage: &StorageAt<'_>,
+    report: &SlashReported,
+    slash_defer_duration: u32,
+) -> Result<Option<(u32, u128)>> {
+    let apply_era = report
+        .slash_era
+        .saturating_add(slash_defer_duration)
+        .saturating_add(1);
+    let unapplied = storage
+        .fetch_or_default(&api::storage().staking().unapplied_slashes(apply_era))
+        .await?;
+    if let Some(pending) = validator_slash(&unapplied, &report.validator) {
+        return Ok(Some((apply_era, pending)));
+    }
+
+    let all_unapplied = api::storage().staking().unapplied_slashes_iter();
+    let mut entries = storage.iter(&all_unapplied).await?;
+    while let Some(entry) = entries.next().await {
+        let (storage_key, unapplied) = entry?;
+        let Some(pending) = validator_slash(&unapplied, &report.validator) else {
+            continue;
+        };
+        let mut encoded_era = storage_key
+            .get(UNAPPLIED_SLASHES_KEY_PREFIX..)
+            .ok_or_else(|| anyhow!("UnappliedSlashes storage key too short"))?;
+        return Ok(Some((u32::decode(&mut encoded_era)?, pending)));
+    }
+    Ok(None)
+}
+
+/// Total of the unapplied slashes of `validator`, including its nominators.
+fn validator_slash(
+    unapplied: &[UnappliedSlash<AccountId32, u128>],
+    validator: &AccountId32,
+) -> Option<u128> {
+    unapplied
+        .iter()
+        .filter(|slash| slash.validator == *validator)
+        .map(|slash| slash.own + slash.others.iter().map(|(_, v)| v).sum::<u128>())
+        .reduce(|total, slash| total + slash)
+}
+
+/// Offence kinds are 16 byte identifiers such as `im-online:offlin`.
+fn offence_kind(kind: &[u8; 16]) -> String {
+    String::from_utf8_lossy(kind)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// ImOnline offences use the session index as time slot; other kinds are shown as hex.
+fn describe_timeslot(kind: &str, timeslot: &[u8]) -> String {
+    if kind.starts_with("im-online") {
+        if let Ok(session) = u32::decode(&mut &timeslot[..]) {
+            return format!("session {}", session);
+        }
+    }
+    format!("time slot {}", hex_string(timeslot))
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
@@ -0,0 +1,252 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve,
+    utils::{
+        account_from_key, api,
+        api::{
+            mandate::{calls::types::Mandate, events::RootOp},
+            runtime_types::{
+                da_runtime::RuntimeCall, pallet_mandate::pallet::Call as MandateCall,
+                sp_runtime::DispatchError,
+            },
+            sudo::{
+                calls::types::{Sudo, SudoAs, SudoUncheckedWeight},
+                events::{KeyChanged, KeyRemoved, Sudid, SudoAsDone},
+            },
+            technical_committee::calls::types::{Close, Execute},
+            tx_pause::events::{CallPaused, CallUnpaused},
+        },
+        AvailConfig,
+    },
+};
+use anyhow::Result;
+use codec::Encode;
+use log::warn;
+use sp_core::H256;
+use subxt::{
+    blocks::{Block, ExtrinsicDetails},
+    client::OnlineClient,
+    events::Events,
+    Metadata,
+};
+
+/// Characters of a dispatched call's arguments shown in an alert, as calls like
+/// `System::set_code` carry large blobs.
+const MAX_ARGS_LEN: usize = 300;
+
+/// `Pallet.call` name of a runtime call, from the pallet and call indexes it is encoded with.
+fn call_name(metadata: &Metadata, call: &RuntimeCall) -> String {
+    let encoded = call.encode();
+    encoded
+        .get(..2)
+        .and_then(|indexes| {
+            let pallet = metadata.pallet_by_index(indexes[0])?;
+            let variant = pallet.call_variant_by_index(indexes[1])?;
+            Some(format!("{}.{}", pallet.name(), variant.name))
+        })
+        .unwrap_or_else(|| "unknown call".to_string())
+}
+
+fn call_args(call: &RuntimeCall) -> String {
+    let args = format!("{:?}", call);
+    match args.char_indices().nth(MAX_ARGS_LEN) {
+        Some((end, _)) => format!("{}…", &args[..end]),
+        None => args,
+    }
+}
+
+fn format_result(result: &Result<(), DispatchError>) -> String {
+    match result {
+        Ok(()) => "Ok".to_string(),
+        Err(e) => format!("Err({:?})", e),
+    }
+}
+
+/// The call a technical committee proposal dispatches with root privileges through `Mandate`.
+fn mandate_call(proposal: RuntimeCall) -> Option<RuntimeCall> {
+    match proposal {
+        RuntimeCall::Mandate(MandateCall::mandate { call }) => Some(*call),
+        _ => None,
+    }
+}
+
+/// The call an extrinsic dispatched with root privileges, when it is a direct sudo or mandate
+/// call, or a mandate proposal executed or closed by the technical committee. Closed proposals
+/// are read at the parent block, as closing removes them.
+async fn dispatched_call(
+    client: &OnlineClient<AvailConfig>,
+    parent_hash: H256,
+    ext: &ExtrinsicDetails<AvailConfig, OnlineClient<AvailConfig>>,
+) -> Result<Option<RuntimeCall>> {
+    if let Some(sudo) = decode::call::<Sudo>(ext)? {
+        return Ok(Some(*sudo.call));
+    }
+    if let Some(sudo) = decode::call::<SudoUncheckedWeight>(ext)? {
+        return Ok(Some(*sudo.call));
+    }
+    if let Some(sudo_as) = decode::call::<SudoAs>(ext)? {
+        return Ok(Some(*sudo_as.call));
+    }
+    if let Some(mandate) = decode::call::<Mandate>(ext)? {
+        return Ok(Some(*mandate.call));
+    }
+    if let Some(execute) = decode::call::<Execute>(ext)? {
+        return Ok(mandate_call(*execute.proposal));
+    }
+    if let Some(close) = decode::call::<Close>(ext)? {
+        let proposal = decode::storage_at(client, parent_hash)
+            .fetch(
+                &api::storage()
+                    .technical_committee()
+                    .proposal_of(close.proposal_hash),
+            )
+            .await?;
+        return Ok(proposal.and_then(mandate_call));
+    }
+    Ok(None)
+}
+
+/// Alerts on every privileged action: sudo and mandate calls with the dispatched call and its
+/// result, sudo key changes, and calls paused or unpaused with `TxPause`.
+pub async fn check_privileged(
+    client: &OnlineClient<AvailConfig>,
+    block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+    events: &Events<AvailConfig>,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    let privileged = decode::has::<Sudid>(events.iter())?
+        || decode::has::<SudoAsDone>(events.iter())?
+        || decode::has::<KeyChanged>(events.iter())?
+        || decode::has::<KeyRemoved>(events.iter())?
+        || decode::has::<RootOp>(events.iter())?
+        || decode::has::<CallPaused>(events.iter())?
+        || decode::has::<CallUnpaused>(events.iter())?;
+    if !privileged {
+        return Ok(());
+    }
+
+    let (hash, number) = (block.hash(), block.number());
+    let metadata = client.metadata();
+    for ext in block.extrinsics().await?.iter() {
+        let ext = ext?;
+        let ext_events = ext.events().await?;
+
+        let mut dispatches = Vec::new();
+        for sudid in decode::find::<Sudid>(ext_events.iter()) {
+            dispatches.push((AlertKind::Sudo, "Sudo call", sudid?.sudo_result));
+        }
+        for done in decode::find::<SudoAsDone>(ext_events.iter()) {
+            dispatches.push((AlertKind::Sudo, "Sudo as call", done?.sudo_result));
+        }
+        for root_op in decode::find::<RootOp>(ext_events.iter()) {
+            dispatches.push((AlertKind::RootOp, "Mandate root operation", root_op?.result));
+        }
+        let key_changes =
+            decode::find::<KeyChanged>(ext_events.iter()).collect::<Result<Vec<_>, _>>()?;
+        let key_removed = decode::has::<KeyRemoved>(ext_events.iter())?;
+        let mut pauses = Vec::new();
+        for paused in decode::find::<CallPaused>(ext_events.iter()) {
+            pauses.push((Severity::Critical, "paused", paused?.full_name));
+        }
+        for unpaused in decode::find::<CallUnpaused>(ext_events.iter()) {
+            pauses.push((Severity::Warning, "unpaused", unpaused?.full_name));
+        }
+        if dispatches.is_empty() && key_changes.is_empty() && !key_removed && pauses.is_empty() {
+            continue;
+        }
+
+        let index = ext.index();
+        let outer_call = format!("{}.{}", ext.pallet_name()?, ext.variant_name()?);
+        let call = dispatched_call(client, block.header().parent_hash, &ext).await?;
+        // Calls dispatched through a batch are shown by their outer call
+        let name = call
+            .as_ref()
+            .map(|call| call_name(&metadata, call))
+            .unwrap_or_else(|| outer_call.clone());
+        let signer = match ext.address_bytes().map(account_from_key) {
+            Some(Ok(account)) => Some(resolve(client, hash, &account).await),
+            Some(Err(e)) => {
+                warn!("Unknown signer of extrinsic {}-{}: {:?}", number, index, e);
+                Some("unknown".to_string())
+            }
+            None => None,
+        };
+        // Batched dispatches share the extrinsic, so their position keeps them apart
+        let with_context = |mut alert: Alert, kind: &str, position: usize| {
+            alert = alert.field(
+                "Extrinsic",
+                format!("{}-{} ({})", number, index, outer_call),
+            );
+            if let Some(signer) = &signer {
+                alert = alert.field("Signer", signer.clone());
+            }
+            alert
+                .block(number)
+                .block_link("Block", number)
+                .dedup_key(format!("{}:{}:{}:{}", kind, number, index, position))
+        };
+
+        for (position, (kind, action, result)) in dispatches.into_iter().enumerate() {
+            let severity = if result.is_ok() {
+                Severity::Warning
+            } else {
+                Severity::Critical
+            };
+            let mut alert = Alert::new(kind, severity, format!("{} {}", action, name))
+                .field("Call", name.clone())
+                .field("Result", format_result(&result));
+            if let Some(call) = &call {
+                alert = alert.field("Arguments", call_args(call));
+            }
+            alerts.notify(with_context(alert, kind.as_str(), position));
+        }
+
+        for (position, key_changed) in key_changes.into_iter().enumerate() {
+            let new = resolve(client, hash, &key_changed.new).await;
+            let old = match &key_changed.old {
+                Some(old) => resolve(client, hash, old).await,
+                None => "none".to_string(),
+            };
+            let alert = Alert::new(
+                AlertKind::SudoKeyChanged,
+                Severity::Critical,
+                format!("Sudo key changed to {}", new),
+            )
+            .field("Old key", old)
+            .field("New key", new);
+            alerts.notify(with_context(alert, "sudo_key_changed", position));
+        }
+
+        if key_removed {
+            let alert = Alert::new(
+                AlertKind::SudoKeyChanged,
+                Severity::Critical,
+                "Sudo key removed",
+            );
+            alerts.notify(with_context(alert, "sudo_key_removed", 0));
+        }
+
+        for (position, (severity, action, (pallet, pallet_call))) in pauses.into_iter().enumerate()
+        {
+            let paused_call = format!(
+                "{}.{}",
+                String::from_utf8_lossy(&pallet.0),
+                String::from_utf8_lossy(&pallet_call.0)
+            );
+            let alert = Alert::new(
+                AlertKind::TxPause,
+                severity,
+                format!("Call {} {}", paused_call, action),
+            )
+            .field("Call", paused_call.clone());
+            alerts.notify(with_context(
+                alert,
+                &format!("tx_pause:{}", paused_call),
+                position,
+            ));
+        }
+    }
+
+    Ok(())
+}
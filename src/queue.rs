@@ -0,0 +1,339 @@
+use crate::{
+    alert::{Alert, Severity},
+    notifier::{Notifier, Notifiers, RetryAfter},
+};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Alerts waiting for a single sink before new ones are dead-lettered.
+const QUEUE_CAPACITY: usize = 1024;
+/// Number of undeliverable alerts kept in the dead-letter log.
+const DEAD_LETTERS_KEPT: usize = 100;
+
+/// How failed deliveries are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff after the given (1-based) failed attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeadLetter {
+    sink: &'static str,
+    failed_at: u64,
+    error: String,
+    alert: Alert,
+}
+
+/// Bounded log of alerts that could not be delivered, optionally mirrored to a JSON lines file.
+#[derive(Clone)]
+struct DeadLetters {
+    entries: Arc<Mutex<VecDeque<DeadLetter>>>,
+    path: Option<PathBuf>,
+}
+
+impl DeadLetters {
+    fn push(&self, sink: &'static str, error: String, alert: Alert) {
+        error!(
+            "Giving up on alert {} for {}: {}",
+            alert.dedup_key, sink, error
+        );
+        let failed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let lines = {
+            let mut entries = self.entries.lock().expect("dead letters lock poisoned");
+            entries.push_back(DeadLetter {
+                sink,
+                failed_at,
+                error,
+                alert,
+            });
+            while entries.len() > DEAD_LETTERS_KEPT {
+                entries.pop_front();
+            }
+            entries
+                .iter()
+                .filter_map(|entry| serde_json::to_string(entry).ok())
+                .collect::<Vec<_>>()
+        };
+
+        if let Some(path) = &self.path {
+            if let Err(e) = fs::write(path, lines.join("\n") + "\n") {
+                error!("Failed to write dead-letter log {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+struct SinkQueue {
+    name: &'static str,
+    min_severity: Severity,
+    route: Option<String>,
+    tx: mpsc::Sender<Alert>,
+}
+
+/// Delivers alerts in the background, with one queue and worker per sink so a slow or
+/// failing sink never delays the others or the chain monitor itself.
+pub struct NotificationQueue {
+    queues: Vec<SinkQueue>,
+    dead_letters: DeadLetters,
+}
+
+impl NotificationQueue {
+    /// Spawns a delivery worker for every sink.
+    pub fn start(
+        notifiers: Notifiers,
+        policy: RetryPolicy,
+        dead_letter_file: Option<PathBuf>,
+    ) -> Self {
+        let dead_letters = DeadLetters {
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+            path: dead_letter_file,
+        };
+        let queues = notifiers
+            .sinks
+            .into_iter()
+            .map(|sink| {
+                let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+                let name = sink.notifier.name();
+                tokio::spawn(deliver(sink.notifier, rx, policy, dead_letters.clone()));
+                SinkQueue {
+                    name,
+                    min_severity: sink.min_severity,
+                    route: sink.route,
+                    tx,
+                }
+            })
+            .collect();
+
+        Self {
+            queues,
+            dead_letters,
+        }
+    }
+
+    /// Queues the alert for every sink whose filter accepts its severity. Routed alerts go to
+    /// the sinks of their route, or to the default sinks when the route has none. Never blocks.
+    pub fn push(&self, alert: &Alert) {
+        let route = alert
+            .route
+            .as_ref()
+            .filter(|route| self.queues.iter().any(|q| q.route.as_ref() == Some(*route)));
+        for queue in self
+            .queues
+            .iter()
+            .filter(|queue| queue.route.as_ref() == route && alert.severity >= queue.min_severity)
+        {
+            match queue.tx.try_send(alert.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(alert)) => self.dead_letters.push(
+                    queue.name,
+                    "notification queue is full".to_string(),
+                    alert,
+                ),
+                Err(TrySendError::Closed(alert)) => self.dead_letters.push(
+                    queue.name,
+                    "notification worker has stopped".to_string(),
+                    alert,
+                ),
+            }
+        }
+    }
+}
+
+/// Delivers queued alerts to a single sink, retrying with exponential backoff or the delay the
+/// sink asked for, and dead-lettering alerts that still fail after the last attempt.
+async fn deliver(
+    notifier: Box<dyn Notifier>,
+    mut rx: mpsc::Receiver<Alert>,
+    policy: RetryPolicy,
+    dead_letters: DeadLetters,
+) {
+    while let Some(alert) = rx.recv().await {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = notifier.notify(&alert).await;
+            match result {
+                Ok(()) => {
+                    info!("Message posted to {} successfully!", notifier.name());
+                    break;
+                }
+                Err(e) if attempt < policy.max_attempts => {
+                    let delay = e
+                        .downcast_ref::<RetryAfter>()
+                        .map(|retry_after| retry_after.0)
+                        .unwrap_or_else(|| policy.backoff(attempt));
+                    warn!(
+                        "Failed to post message to {} (attempt {}/{}), retrying in {:?}: {:?}",
+                        notifier.name(),
+                        attempt,
+                        policy.max_attempts,
+                        delay,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    dead_letters.push(notifier.name(), format!("{:?}", e), alert);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::AlertKind, notifier::Sink};
+    use anyhow::{bail, Result};
+    use futures::future::BoxFuture;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Sink that fails its first `failures` deliveries and counts every attempt.
+    struct StubNotifier {
+        name: &'static str,
+        failures: u32,
+        attempts: Arc<AtomicU32>,
+        delivered: Arc<AtomicU32>,
+    }
+
+    impl Notifier for StubNotifier {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn notify<'a>(&'a self, _alert: &'a Alert) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                if attempt <= self.failures {
+                    bail!("stub failure {}", attempt);
+                }
+                self.delivered.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        }
+    }
+
+    struct Counters {
+        attempts: Arc<AtomicU32>,
+        delivered: Arc<AtomicU32>,
+    }
+
+    fn stub(name: &'static str, failures: u32, notifiers: &mut Notifiers) -> Counters {
+        let counters = Counters {
+            attempts: Arc::new(AtomicU32::new(0)),
+            delivered: Arc::new(AtomicU32::new(0)),
+        };
+        notifiers.sinks.push(Sink {
+            min_severity: Severity::Info,
+            route: None,
+            notifier: Box::new(StubNotifier {
+                name,
+                failures,
+                attempts: counters.attempts.clone(),
+                delivered: counters.delivered.clone(),
+            }),
+        });
+        counters
+    }
+
+    fn policy(max_attempts: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: backoff,
+            max_backoff: backoff,
+        }
+    }
+
+    fn alert(title: &str) -> Alert {
+        Alert::new(AlertKind::EpochBlockProduction, Severity::Warning, title)
+    }
+
+    fn dead_letter_count(queue: &NotificationQueue) -> usize {
+        queue
+            .dead_letters
+            .entries
+            .lock()
+            .expect("dead letters lock poisoned")
+            .len()
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("condition not met in time");
+    }
+
+    #[tokio::test]
+    async fn retries_until_delivered() {
+        let mut notifiers = Notifiers::default();
+        let counters = stub("stub", 2, &mut notifiers);
+        let queue = NotificationQueue::start(notifiers, policy(5, Duration::from_millis(1)), None);
+
+        queue.push(&alert("flaky"));
+        wait_until(|| counters.delivered.load(Ordering::SeqCst) == 1).await;
+
+        assert_eq!(counters.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(dead_letter_count(&queue), 0);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_after_max_attempts() {
+        let mut notifiers = Notifiers::default();
+        let counters = stub("stub", u32::MAX, &mut notifiers);
+        let queue = NotificationQueue::start(notifiers, policy(3, Duration::from_millis(1)), None);
+
+        queue.push(&alert("broken"));
+        wait_until(|| dead_letter_count(&queue) == 1).await;
+
+        assert_eq!(counters.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(counters.delivered.load(Ordering::SeqCst), 0);
+        let entries = queue.dead_letters.entries.lock().unwrap();
+        assert_eq!(entries[0].sink, "stub");
+        assert!(entries[0].error.contains("stub failure 3"));
+    }
+
+    #[tokio::test]
+    async fn failing_sink_does_not_block_others() {
+        let mut notifiers = Notifiers::default();
+        let failing = stub("failing", u32::MAX, &mut notifiers);
+        let healthy = stub("healthy", 0, &mut notifiers);
+        // The failing sink waits a minute before its retry, far longer than the test
+        let queue = NotificationQueue::start(notifiers, policy(2, Duration::from_secs(60)), None);
+
+        queue.push(&alert("first"));
+        queue.push(&alert("second"));
+        wait_until(|| healthy.delivered.load(Ordering::SeqCst) == 2).await;
+
+        assert_eq!(failing.attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(dead_letter_count(&queue), 0);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    metadata::fetch_v15,
+    utils::{api, AvailConfig},
+};
+use anyhow::Result;
+use codec::Decode;
+use log::error;
+use sp_core::H256;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    blocks::Block,
+    client::OnlineClient,
+    config::substrate::DigestItem,
+    Metadata,
+};
+
+/// Detects runtime upgrades and checks that the live metadata still matches the one `api` was
+/// generated from, as changed pallets are only decoded through the dynamic fallback otherwise.
+pub struct RuntimeMonitor {
+    rpc: LegacyRpcMethods<AvailConfig>,
+    spec_version: Option<u32>,
+}
+
+impl RuntimeMonitor {
+    pub fn new(rpc_client: RpcClient) -> Self {
+        Self {
+            rpc: LegacyRpcMethods::new(rpc_client),
+            spec_version: None,
+        }
+    }
+
+    /// Compares the metadata at a block, or at the best block, with the bundled
+    /// `artifacts/polkadot_metadata.scale`.
+    pub async fn check_metadata(
+        &self,
+        at: Option<(H256, u32)>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let bytes = fetch_v15(&self.rpc, at.map(|(hash, _)| hash)).await?;
+        let metadata = Metadata::decode(&mut &bytes[..])?;
+        if api::is_codegen_valid_for(&metadata) {
+            alerts.resolve(AlertKind::MetadataDrift.as_str());
+            return Ok(());
+        }
+
+        error!(
+            "Runtime metadata does not match artifacts/polkadot_metadata.scale, changed events \
+             and storage are decoded dynamically. Regenerate src/avail_api.rs."
+        );
+        let mut alert = Alert::new(
+            AlertKind::MetadataDrift,
+            Severity::Critical,
+            "Runtime metadata differs from the bundled metadata",
+        )
+        .message(
+            "Checks decode changed pallets from the live metadata until the monitor is rebuilt.",
+        );
+        if let Some((_, number)) = at {
+            alert = alert.block(number).block_link("Checked at", number);
+        }
+        alerts.fire(alert);
+        Ok(())
+    }
+
+    /// Alerts when the spec version in `System::LastRuntimeUpgrade` changes or a block carries
+    /// a `RuntimeEnvironmentUpdated` digest, and checks the metadata again.
+    pub async fn on_finalized(
+        &mut self,
+        client: &OnlineClient<AvailConfig>,
+        block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let (hash, number) = (block.hash(), block.number());
+        let environment_updated = block
+            .header()
+            .digest
+            .logs
+            .iter()
+            .any(|log| matches!(log, DigestItem::RuntimeEnvironmentUpdated));
+        let Some(last_upgrade) = decode::storage_at(client, hash)
+            .fetch(&api::storage().system().last_runtime_upgrade())
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let spec_version = last_upgrade.spec_version;
+        let previous = self.spec_version.replace(spec_version);
+        let upgraded = previous.is_some_and(|previous| previous != spec_version);
+        if upgraded {
+            let alert = Alert::new(
+                AlertKind::RuntimeUpgrade,
+                Severity::Warning,
+                format!(
+                    "Runtime upgraded to {} spec version {}",
+                    last_upgrade.spec_name, spec_version
+                ),
+            )
+            .field("Spec name", last_upgrade.spec_name.clone())
+            .field("Spec version", spec_version.to_string())
+            .field(
+                "Previous spec version",
+                previous.unwrap_or_default().to_string(),
+            )
+            .block(number)
+            .block_link("Upgraded in", number)
+            .dedup_key(format!("runtime_upgrade:{}", spec_version));
+            alerts.notify(alert);
+        } else if environment_updated {
+            let alert = Alert::new(
+                AlertKind::RuntimeUpgrade,
+                Severity::Info,
+                format!("Runtime environment updated in block #{}", number),
+            )
+            .field("Spec version", spec_version.to_string())
+            .block(number)
+            .block_link("Updated in", number)
+            .dedup_key(format!("runtime_environment_updated:{}", number));
+            alerts.notify(alert);
+        }
+
+        if upgraded || environment_updated {
+            self.check_metadata(Some((hash, number)), alerts).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::avail_api;
+use crate::{avail_api, notifier::SinkConfig};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -9,6 +10,19 @@ pub struct Opts {
     #[structopt(long, default_value = "3030")]
     pub health_port: u16,
 
+    /// JSON file mapping stash accounts to validator names, used for accounts without an
+    /// on-chain identity
+    #[structopt(long, parse(from_os_str), default_value = "offchain_identities.json")]
+    pub identities_file: PathBuf,
+
+    /// Seconds a resolved identity is reused before it is looked up again
+    #[structopt(long, default_value = "3600")]
+    pub identity_ttl: u64,
+
+    /// JSON file mapping hashed identity display names (`0x<hash>`) to their preimages
+    #[structopt(long, parse(from_os_str))]
+    pub identity_preimages: Option<PathBuf>,
+
     #[structopt(subcommand)]
     pub command: Command,
 }
@@ -33,13 +47,165 @@ pub enum Command {
         #[structopt(help = "Block number at which epoch started")]
         block_id: u32,
     },
-    /// Monitors chain to determine number of blocks produced in an epoch/era when it ends
-    ChainMonitor {
-        /// Optional Slack channel ID. If specified, chain information will be posted to Slack.
-        /// Ensure the SLACK_TOKEN env is set.
-        #[structopt(short, long)]
-        channel_id: Option<String>,
+    /// Report each validator's reward points, rank and deviation from the median for an era
+    EraReport {
+        #[structopt(help = "Era index")]
+        era: u32,
+    },
+    /// Report data submissions per block and per app ID, and the top submitters, for a block range
+    DaStats {
+        #[structopt(help = "First block number")]
+        from: u32,
+        #[structopt(help = "Last block number")]
+        to: u32,
+    },
+    /// List registered application keys with their owner and the block that created them
+    AppKeys {
+        #[structopt(long, help = "Only list keys owned by this account")]
+        owner: Option<String>,
+        #[structopt(long, help = "Only list the key with this app ID")]
+        id: Option<u32>,
+    },
+    /// Decode a block's header extension: Kate commitment, data root and app data lookup
+    Header {
+        #[structopt(help = "Block number")]
+        block: u32,
+    },
+    /// Rebuild each block's data root from its data submissions and bridge messages and report
+    /// blocks where it differs from the header
+    VerifyDataRoot {
+        #[structopt(help = "First block number")]
+        from: u32,
+        #[structopt(help = "Last block number")]
+        to: u32,
+    },
+    /// Find a Vector bridge message and show its lifecycle: submission, execution, status and
+    /// failure reason
+    VectorMessage {
+        #[structopt(help = "Message ID, message root, extrinsic hash or <block>-<index>")]
+        query: String,
+        #[structopt(long, help = "First block to search, defaults to a day before --to")]
+        from: Option<u32>,
+        #[structopt(long, help = "Last block to search, defaults to the finalized head")]
+        to: Option<u32>,
+    },
+    /// Downloads the runtime metadata and prints what changed since the bundled metadata
+    UpdateMetadata {
+        #[structopt(
+            long,
+            parse(from_os_str),
+            default_value = "artifacts/polkadot_metadata.scale",
+            help = "Metadata file to update"
+        )]
+        path: PathBuf,
+        #[structopt(long, help = "Print the changes without writing the file")]
+        dry_run: bool,
     },
+    /// Monitors chain to determine number of blocks produced in an epoch/era when it ends
+    ChainMonitor(MonitorOpts),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MonitorOpts {
+    /// Optional Slack channel ID. If specified, chain information will be posted to Slack.
+    /// Ensure the SLACK_TOKEN env is set.
+    #[structopt(short, long)]
+    pub channel_id: Option<String>,
+
+    /// Additional notification sink as `<kind>[@<min-severity>]=<target>`. May be repeated.
+    /// Kinds: slack, slack-webhook, discord, telegram, matrix, webhook, pagerduty.
+    #[structopt(long = "notify")]
+    pub notify: Vec<SinkConfig>,
+
+    /// Block explorer URL that block numbers are appended to for links in Slack messages,
+    /// e.g. https://avail-turing.subscan.io/block
+    #[structopt(long)]
+    pub explorer_url: Option<String>,
+
+    /// Suppress repeats of the same alert within this many seconds
+    #[structopt(long, default_value = "600")]
+    pub dedup_window: u64,
+
+    /// Fraction of a session after which validators with no ImOnline heartbeat and no
+    /// authored blocks are reported
+    #[structopt(long, default_value = "0.5")]
+    pub heartbeat_threshold: f64,
+
+    /// Alert when the finalized head is more than this many blocks behind the best block
+    #[structopt(long, default_value = "20")]
+    pub finality_lag_blocks: u32,
+
+    /// Alert when no block has been finalized for this many seconds
+    #[structopt(long, default_value = "300")]
+    pub finality_lag_secs: u64,
+
+    /// Alert when no new best block arrives within this many slot durations
+    #[structopt(long, default_value = "10")]
+    pub stall_slots: u32,
+
+    /// Number of most recent slots the block production rate is computed over
+    #[structopt(long, default_value = "120")]
+    pub rate_window_slots: u64,
+
+    /// Alert when less than this share of slots in the window produced a block
+    #[structopt(long, default_value = "0.8")]
+    pub min_production_rate: f64,
+
+    /// Number of most recent finalized blocks the average data matrix fullness is computed over
+    #[structopt(long, default_value = "100")]
+    pub fullness_window: usize,
+
+    /// Alert when the average data matrix fullness over the window reaches this share
+    #[structopt(long, default_value = "0.8")]
+    pub fullness_threshold: f64,
+
+    /// Alert when the Ethereum head followed by the Vector bridge has not advanced for this many
+    /// seconds
+    #[structopt(long, default_value = "7200")]
+    pub vector_head_stall_secs: u64,
+
+    /// Delivery attempts per notification and sink before it is dead-lettered
+    #[structopt(long, default_value = "5")]
+    pub notify_attempts: u32,
+
+    /// File that keeps the most recent undeliverable notifications as JSON lines
+    #[structopt(long, parse(from_os_str))]
+    pub dead_letter_file: Option<PathBuf>,
+
+    /// Comma separated SS58 addresses of validators to report on individually
+    #[structopt(long, use_delimiter = true)]
+    pub watch: Vec<String>,
+
+    /// File with one watched validator per line, as `<ss58> [<kind>[@<min-severity>]=<target> ...]`.
+    /// Alerts about a validator go to its own sinks if it lists any.
+    #[structopt(long, parse(from_os_str))]
+    pub watch_file: Option<PathBuf>,
+}
+
+/// Number of decimals of the AVAIL token.
+pub const AVAIL_DECIMALS: u32 = 18;
+
+/// Formats a balance in the smallest unit as AVAIL with two decimals.
+pub fn format_avail(amount: u128) -> String {
+    let unit = 10u128.pow(AVAIL_DECIMALS);
+    let cents = (amount % unit) / 10u128.pow(AVAIL_DECIMALS - 2);
+    format!("{}.{:02} AVAIL", amount / unit, cents)
+}
+
+/// Formats a `Perbill` in parts per billion as a percentage.
+pub fn format_perbill(parts: u32) -> String {
+    format!("{:.4}%", f64::from(parts) / 10_000_000.0)
+}
+
+/// Account from a storage key whose last hasher is `Twox64Concat` or `Blake2_128Concat`.
+pub fn account_from_key(key: &[u8]) -> anyhow::Result<AccountId32> {
+    let start = key
+        .len()
+        .checked_sub(32)
+        .ok_or_else(|| anyhow::anyhow!("Storage key too short for an account"))?;
+    let mut account = [0u8; 32];
+    account.copy_from_slice(&key[start..]);
+    Ok(AccountId32(account))
 }
 
 // #[subxt::subxt(runtime_metadata_path = "./artifacts/polkadot_metadata.scale")]
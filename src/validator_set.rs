@@ -0,0 +1,204 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve_all,
+    utils::{
+        account_from_key, api,
+        api::{session::events::NewSession, staking::events::EraPaid},
+        format_avail, format_perbill, AvailConfig,
+    },
+};
+use anyhow::Result;
+use futures::future::join_all;
+use sp_core::H256;
+use std::collections::BTreeSet;
+use subxt::{client::OnlineClient, events::Events, utils::AccountId32};
+
+/// Reports validator set changes: an early warning when the queued set for the next session
+/// differs from the current one, and the actual change once the new era starts.
+pub async fn check_validator_set(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    block_number: u32,
+    events: &Events<AvailConfig>,
+    alerts: &mut AlertManager,
+) -> Result<()> {
+    let storage = decode::storage_at(client, block_hash);
+
+    if let Some(era_paid) = decode::find_first::<EraPaid>(events.iter())? {
+        // The session rotated to the new era's set in this block, compare it with the
+        // validators elected for the era that just ended
+        let previous_era = era_paid.era_index;
+        let era = previous_era + 1;
+        let current: BTreeSet<AccountId32> = storage
+            .fetch_or_default(&api::storage().session().validators())
+            .await?
+            .into_iter()
+            .collect();
+        let previous = era_validators(client, block_hash, previous_era).await?;
+
+        let joined: Vec<_> = current.difference(&previous).cloned().collect();
+        let left: Vec<_> = previous.difference(&current).cloned().collect();
+        if !joined.is_empty() || !left.is_empty() {
+            let joined = describe_all(client, block_hash, era, &joined).await?;
+            let left = describe_all(client, block_hash, previous_era, &left).await?;
+            let alert = Alert::new(
+                AlertKind::ValidatorSetChange,
+                Severity::Info,
+                format!("Era {} validator set changes", era),
+            )
+            .message(change_message(("Joined", &joined), ("Left", &left)))
+            .field("Validators", current.len().to_string())
+            .field("Joined", joined.len().to_string())
+            .field("Left", left.len().to_string())
+            .era(era)
+            .block(block_number)
+            .block_link("Era start", block_number)
+            .dedup_key(format!("validator_set_change:{}", era));
+            alerts.notify(alert);
+        }
+    }
+
+    if let Some(new_session) = decode::find_first::<NewSession>(events.iter())? {
+        let next_session = new_session.session_index + 1;
+        let current: BTreeSet<AccountId32> = storage
+            .fetch_or_default(&api::storage().session().validators())
+            .await?
+            .into_iter()
+            .collect();
+        let queued: BTreeSet<AccountId32> = storage
+            .fetch_or_default(&api::storage().session().queued_keys())
+            .await?
+            .into_iter()
+            .map(|(account, _)| account)
+            .collect();
+
+        let joining: Vec<_> = queued.difference(&current).cloned().collect();
+        let leaving: Vec<_> = current.difference(&queued).cloned().collect();
+        if !joining.is_empty() || !leaving.is_empty() {
+            // The queued set was elected for the planned era, the current one for the active era
+            let planned_era = storage
+                .fetch(&api::storage().staking().current_era())
+                .await?;
+            let active_era = storage
+                .fetch(&api::storage().staking().active_era())
+                .await?
+                .map(|era| era.index);
+            let joining = match planned_era {
+                Some(era) => describe_all(client, block_hash, era, &joining).await?,
+                None => resolve_all(client, block_hash, &joining).await,
+            };
+            let leaving = match active_era {
+                Some(era) => describe_all(client, block_hash, era, &leaving).await?,
+                None => resolve_all(client, block_hash, &leaving).await,
+            };
+
+            let mut alert = Alert::new(
+                AlertKind::UpcomingValidatorSet,
+                Severity::Info,
+                format!("Validator set changes queued for session {}", next_session),
+            )
+            .message(change_message(("Joining", &joining), ("Leaving", &leaving)))
+            .field("Session", next_session.to_string())
+            .field("Validators", queued.len().to_string())
+            .field("Joining", joining.len().to_string())
+            .field("Leaving", leaving.len().to_string())
+            .epoch(new_session.session_index.into())
+            .block(block_number)
+            .dedup_key(format!("upcoming_validator_set:{}", next_session));
+            if let Some(era) = planned_era {
+                alert = alert.era(era);
+            }
+            alerts.notify(alert);
+        }
+    }
+
+    Ok(())
+}
+
+/// Validators elected for `era`, from `Staking::ErasValidatorPrefs`.
+async fn era_validators(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    era: u32,
+) -> Result<BTreeSet<AccountId32>> {
+    let mut validators = BTreeSet::new();
+    let mut prefs = decode::storage_at(client, block_hash)
+        .keys(&api::storage().staking().eras_validator_prefs_iter1(era))
+        .await?;
+    while let Some(entry) = prefs.next().await {
+        let key = entry?;
+        validators.insert(account_from_key(&key)?);
+    }
+    Ok(validators)
+}
+
+/// Self stake, total stake and commission of a validator in `era`, after its name.
+async fn describe(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    era: u32,
+    account: &AccountId32,
+    name: String,
+) -> Result<String> {
+    let storage = decode::storage_at(client, block_hash);
+    let exposure = storage
+        .fetch(&api::storage().staking().eras_stakers_overview(era, account))
+        .await?;
+    let prefs = storage
+        .fetch(&api::storage().staking().eras_validator_prefs(era, account))
+        .await?;
+
+    let mut details = Vec::new();
+    if let Some(exposure) = exposure {
+        details.push(format!("self {}", format_avail(exposure.own)));
+        details.push(format!("total {}", format_avail(exposure.total)));
+        details.push(format!("{} nominators", exposure.nominator_count));
+    }
+    if let Some(prefs) = prefs {
+        details.push(format!("commission {}", format_perbill(prefs.commission.0)));
+    }
+    if details.is_empty() {
+        return Ok(name);
+    }
+    Ok(format!("{}: {}", name, details.join(", ")))
+}
+
+async fn describe_all(
+    client: &OnlineClient<AvailConfig>,
+    block_hash: H256,
+    era: u32,
+    accounts: &[AccountId32],
+) -> Result<Vec<String>> {
+    let names = resolve_all(client, block_hash, accounts).await;
+    join_all(
+        accounts
+            .iter()
+            .zip(names)
+            .map(|(account, name)| describe(client, block_hash, era, account, name)),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+fn change_message(joining: (&str, &[String]), leaving: (&str, &[String])) -> String {
+    let list = |validators: &[String]| {
+        if validators.is_empty() {
+            "none".to_string()
+        } else {
+            validators
+                .iter()
+                .map(|v| format!("• {}", v))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    format!(
+        "{}:\n{}\n{}:\n{}",
+        joining.0,
+        list(joining.1),
+        leaving.0,
+        list(leaving.1)
+    )
+}
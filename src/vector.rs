@@ -0,0 +1,464 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    data_root::AddressedMessage,
+    decode, metrics,
+    utils::{
+        api,
+        api::{
+            staking::events::EraPaid,
+            system::events::ExtrinsicFailed,
+            vector::{
+                calls::types::{Execute, FailedSendMessageTxs, SendMessage},
+                events::{
+                    MessageExecuted, MessageSubmitted, SourceChainFrozen, SyncCommitteeUpdated,
+                },
+            },
+        },
+        AvailConfig, Opts,
+    },
+};
+use anyhow::{bail, Context, Result};
+use log::info;
+use sp_core::H256;
+use sp_crypto_hashing::{blake2_256, keccak_256};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    blocks::Block,
+    client::OnlineClient,
+    events::Events,
+};
+
+/// Watches the Vector bridge: the Ethereum head it follows, frozen source chains, failed
+/// `send_message` transactions and message throughput per era.
+pub struct VectorMonitor {
+    /// Time without a new Ethereum head before the bridge is considered stalled.
+    max_head_age: Duration,
+    head: Option<u64>,
+    head_updated_at: Instant,
+    submitted: u64,
+    executed: u64,
+    /// First block of the era being counted, once an era boundary has been seen, so that every
+    /// report covers a whole era rather than the time since the monitor started.
+    counted_since: Option<u32>,
+}
+
+impl VectorMonitor {
+    pub fn new(max_head_age: Duration) -> Self {
+        Self {
+            max_head_age,
+            head: None,
+            head_updated_at: Instant::now(),
+            submitted: 0,
+            executed: 0,
+            counted_since: None,
+        }
+    }
+
+    pub async fn on_block(
+        &mut self,
+        client: &OnlineClient<AvailConfig>,
+        block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+        events: &Events<AvailConfig>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let number = block.number();
+        self.check_head(client, block, alerts).await?;
+
+        for frozen in decode::find::<SourceChainFrozen>(events.iter()) {
+            let frozen = frozen?;
+            let dedup_key = format!(
+                "{}:{}",
+                AlertKind::VectorSourceChainFrozen.as_str(),
+                frozen.source_chain_id
+            );
+            if !frozen.frozen {
+                alerts.resolve(&dedup_key);
+                continue;
+            }
+            let alert = Alert::new(
+                AlertKind::VectorSourceChainFrozen,
+                Severity::Critical,
+                format!("Vector source chain {} frozen", frozen.source_chain_id),
+            )
+            .field("Source chain", frozen.source_chain_id.to_string())
+            .block(number)
+            .block_link("Frozen in", number)
+            .dedup_key(dedup_key);
+            alerts.fire(alert);
+        }
+
+        if let Some(updated) = decode::find_first::<SyncCommitteeUpdated>(events.iter())? {
+            let alert = Alert::new(
+                AlertKind::VectorSyncCommittee,
+                Severity::Info,
+                format!(
+                    "Vector sync committee updated for period {}",
+                    updated.period
+                ),
+            )
+            .field("Period", updated.period.to_string())
+            .block(number)
+            .block_link("Updated in", number)
+            .dedup_key(format!("vector_sync_committee:{}", updated.period));
+            alerts.notify(alert);
+        }
+
+        for ext in block.extrinsics().await?.iter() {
+            let Some(failed) = decode::call::<FailedSendMessageTxs>(&ext?)? else {
+                continue;
+            };
+            if failed.failed_txs.is_empty() {
+                continue;
+            }
+            let indexes: Vec<String> = failed
+                .failed_txs
+                .iter()
+                .map(|index| index.0.to_string())
+                .collect();
+            metrics::inc_counter(
+                "avail_vector_failed_send_messages_total",
+                "Failed Vector send_message transactions",
+                &[],
+                indexes.len() as f64,
+            );
+            let alert = Alert::new(
+                AlertKind::VectorFailedMessages,
+                Severity::Warning,
+                format!(
+                    "{} Vector send_message transactions failed in block #{}",
+                    indexes.len(),
+                    number
+                ),
+            )
+            .field("Extrinsics", indexes.join(", "))
+            .block(number)
+            .block_link("Block", number)
+            .dedup_key(format!("vector_failed_messages:{}", number));
+            alerts.notify(alert);
+        }
+
+        let submitted = decode::find::<MessageSubmitted>(events.iter()).count() as u64;
+        let executed = decode::find::<MessageExecuted>(events.iter()).count() as u64;
+        metrics::inc_counter(
+            "avail_vector_messages_total",
+            "Vector bridge messages by direction",
+            &[("direction", "submitted")],
+            submitted as f64,
+        );
+        metrics::inc_counter(
+            "avail_vector_messages_total",
+            "Vector bridge messages by direction",
+            &[("direction", "executed")],
+            executed as f64,
+        );
+        self.submitted += submitted;
+        self.executed += executed;
+
+        if let Some(era_paid) = decode::find_first::<EraPaid>(events.iter())? {
+            let Some(counted_since) = self.counted_since.replace(number + 1) else {
+                info!(
+                    "Skipping Vector throughput of era {}, the monitor started during it",
+                    era_paid.era_index
+                );
+                self.submitted = 0;
+                self.executed = 0;
+                return Ok(());
+            };
+            for (direction, count) in [("submitted", self.submitted), ("executed", self.executed)] {
+                metrics::set_gauge(
+                    "avail_vector_era_messages",
+                    "Vector bridge messages by direction in the last complete era",
+                    &[("direction", direction)],
+                    count as f64,
+                );
+            }
+            info!(
+                "Vector messages in era {}: {} submitted, {} executed",
+                era_paid.era_index, self.submitted, self.executed
+            );
+            let alert = Alert::new(
+                AlertKind::VectorThroughput,
+                Severity::Info,
+                format!(
+                    "Vector bridge in era {}: {} messages submitted, {} executed",
+                    era_paid.era_index, self.submitted, self.executed
+                ),
+            )
+            .field("Submitted", self.submitted.to_string())
+            .field("Executed", self.executed.to_string())
+            .era(era_paid.era_index)
+            .block(number)
+            .block_link("Counted since", counted_since);
+            alerts.notify(alert);
+            self.submitted = 0;
+            self.executed = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Alerts when the Ethereum head the bridge follows has not advanced for too long, and
+    /// resolves the alert once it does.
+    async fn check_head(
+        &mut self,
+        client: &OnlineClient<AvailConfig>,
+        block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let storage = decode::storage_at(client, block.hash());
+        let head = storage
+            .fetch_or_default(&api::storage().vector().head())
+            .await?;
+        if self.head != Some(head) {
+            self.head = Some(head);
+            self.head_updated_at = Instant::now();
+            metrics::set_gauge(
+                "avail_vector_head_slot",
+                "Ethereum slot of the Vector bridge head",
+                &[],
+                head as f64,
+            );
+        }
+
+        let since_update = self.head_updated_at.elapsed();
+        if since_update <= self.max_head_age {
+            alerts.resolve(AlertKind::VectorHeadStalled.as_str());
+            return Ok(());
+        }
+        let mut alert = Alert::new(
+            AlertKind::VectorHeadStalled,
+            Severity::Critical,
+            format!(
+                "Vector head stuck at slot {} for {}s",
+                head,
+                since_update.as_secs()
+            ),
+        )
+        .field("Head slot", head.to_string())
+        .field("Since update", format!("{}s", since_update.as_secs()))
+        .field("Threshold", format!("{}s", self.max_head_age.as_secs()))
+        .block(block.number());
+        let timestamp = storage
+            .fetch_or_default(&api::storage().vector().timestamps(head))
+            .await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if timestamp > 0 {
+            alert = alert.field("Head age", format!("{}s", now.saturating_sub(timestamp)));
+        }
+        alerts.fire(alert);
+        Ok(())
+    }
+}
+
+/// Blocks searched back from the finalized head when no range is given, about a day.
+const SEARCH_BLOCKS: u32 = 4320;
+
+/// A bridge message looked up by ID, message root, extrinsic hash or `<block>-<index>`.
+#[derive(Debug, Clone, Copy)]
+enum MessageQuery {
+    Id(u64),
+    Hash(H256),
+    Extrinsic(u32, u32),
+}
+
+impl FromStr for MessageQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        if let Some((block, index)) = query.split_once('-') {
+            return Ok(MessageQuery::Extrinsic(block.parse()?, index.parse()?));
+        }
+        if query.starts_with("0x") {
+            return Ok(MessageQuery::Hash(H256::from_str(query)?));
+        }
+        query
+            .parse()
+            .map(MessageQuery::Id)
+            .with_context(|| format!("Invalid message ID, hash or extrinsic {}", query))
+    }
+}
+
+/// Finds a bridge message and prints its lifecycle: the extrinsic that submitted or executed
+/// it, its status and why it failed.
+pub async fn print_message(query: String, from: Option<u32>, to: Option<u32>) -> Result<()> {
+    let query = MessageQuery::from_str(&query)?;
+
+    let args = Opts::from_args();
+    let rpc_client = RpcClient::from_url(args.ws.clone()).await?;
+    let rpc = LegacyRpcMethods::<AvailConfig>::new(rpc_client.clone());
+    let client = OnlineClient::<AvailConfig>::from_rpc_client(rpc_client).await?;
+    let avail_domain = decode::constant(&client, &api::constants().vector().avail_domain())?;
+
+    let finalized_hash = rpc.chain_get_finalized_head().await?;
+    let finalized = client.blocks().at(finalized_hash).await?.number();
+    let blocks: Vec<u32> = match query {
+        MessageQuery::Extrinsic(block, _) => vec![block],
+        _ => {
+            let to = to.unwrap_or(finalized).min(finalized);
+            let from = from.unwrap_or(to.saturating_sub(SEARCH_BLOCKS));
+            if from > to {
+                bail!("from should be less than or equal to to.");
+            }
+            let mut blocks: Vec<u32> = (from..=to).rev().collect();
+            // Messages sent from Avail are numbered `block << 32 | extrinsic index`
+            if let MessageQuery::Id(id) = query {
+                let block = (id >> 32) as u32;
+                if block <= finalized && !(from..=to).contains(&block) {
+                    blocks.insert(0, block);
+                }
+            }
+            blocks
+        }
+    };
+
+    let mut found = 0;
+    for number in blocks {
+        let block_hash = rpc
+            .chain_get_block_hash(Some(number.into()))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Block hash not found for number: {}", number))?;
+        let block = client.blocks().at(block_hash).await?;
+        let extrinsics = block.extrinsics().await?;
+
+        let mut failed_sends = Vec::new();
+        for ext in extrinsics.iter() {
+            if let Some(failed) = decode::call::<FailedSendMessageTxs>(&ext?)? {
+                failed_sends.extend(failed.failed_txs.iter().map(|index| index.0));
+            }
+        }
+
+        for ext in extrinsics.iter() {
+            let ext = ext?;
+            let tx_hash = H256(blake2_256(ext.bytes()));
+            let events = ext.events().await?;
+            let submitted = decode::find_first::<MessageSubmitted>(events.iter())?;
+            let executed = decode::find_first::<MessageExecuted>(events.iter())?;
+            let send = decode::call::<SendMessage>(&ext)?;
+            let execute = decode::call::<Execute>(&ext)?;
+            let execute_root = execute.as_ref().map(|call| {
+                let message = &call.addr_message;
+                H256(keccak_256(
+                    &AddressedMessage {
+                        message: &message.message,
+                        from: message.from,
+                        to: message.to,
+                        origin_domain: message.origin_domain,
+                        destination_domain: message.destination_domain,
+                        id: message.id,
+                    }
+                    .abi_encode(),
+                ))
+            });
+
+            let matched = match query {
+                MessageQuery::Extrinsic(_, index) => ext.index() == index,
+                MessageQuery::Hash(hash) => {
+                    tx_hash == hash
+                        || executed.as_ref().is_some_and(|e| e.message_root == hash)
+                        || execute_root == Some(hash)
+                }
+                MessageQuery::Id(id) => {
+                    submitted.as_ref().is_some_and(|e| e.message_id == id)
+                        || executed.as_ref().is_some_and(|e| e.message_id == id)
+                        || execute
+                            .as_ref()
+                            .is_some_and(|call| call.addr_message.id == id)
+                        || (send.is_some()
+                            && id == ((u64::from(number) << 32) | u64::from(ext.index())))
+                }
+            };
+            if !matched {
+                continue;
+            }
+            found += 1;
+
+            println!("#{} extrinsic {} ({:?})", number, ext.index(), tx_hash);
+            if let Some(call) = &send {
+                println!(
+                    "    Send message to {:?} on domain {} (Avail domain {})",
+                    call.to, call.domain, avail_domain
+                );
+            }
+            if let Some(submitted) = &submitted {
+                println!(
+                    "    Submitted: ID {}, from {}, to {:?}, {:?}, destination domain {}",
+                    submitted.message_id,
+                    submitted.from,
+                    submitted.to,
+                    submitted.message_type,
+                    submitted.destination_domain
+                );
+            }
+            if let Some(call) = &execute {
+                println!(
+                    "    Execute message {} from {:?} on domain {} at Ethereum slot {}",
+                    call.addr_message.id,
+                    call.addr_message.from,
+                    call.addr_message.origin_domain,
+                    call.slot
+                );
+            }
+            if let Some(executed) = &executed {
+                println!(
+                    "    Executed: ID {}, from {:?}, to {:?}, root {:?}",
+                    executed.message_id, executed.from, executed.to, executed.message_root
+                );
+            }
+            if let Some(root) = executed.as_ref().map(|e| e.message_root).or(execute_root) {
+                let status = decode::storage_at(&client, finalized_hash)
+                    .fetch_or_default(&api::storage().vector().message_status(root))
+                    .await?;
+                println!("    Status: {:?}", status);
+            }
+            if let Some(failed) = decode::find_first::<ExtrinsicFailed>(events.iter())? {
+                println!("    Failed: {:?}", failed.dispatch_error);
+            }
+            if failed_sends.contains(&ext.index()) {
+                println!("    Failed: listed in failed_send_message_txs");
+            }
+        }
+    }
+
+    if found == 0 {
+        println!("No matching bridge message found");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_message_queries() {
+        assert!(matches!(
+            MessageQuery::from_str("42").unwrap(),
+            MessageQuery::Id(42)
+        ));
+        assert!(matches!(
+            MessageQuery::from_str("120-3").unwrap(),
+            MessageQuery::Extrinsic(120, 3)
+        ));
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert!(matches!(
+            MessageQuery::from_str(&hash).unwrap(),
+            MessageQuery::Hash(h) if h == H256::repeat_byte(0xab)
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_message_queries() {
+        assert!(MessageQuery::from_str("abc").is_err());
+        assert!(MessageQuery::from_str("0x1234").is_err());
+        assert!(MessageQuery::from_str("1-x").is_err());
+    }
+}
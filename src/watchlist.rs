@@ -0,0 +1,331 @@
+use crate::{
+    alert::{Alert, AlertKind, AlertManager, Severity},
+    decode,
+    identity::resolve,
+    notifier::{Notifiers, SinkConfig},
+    utils::{
+        api,
+        api::{
+            session::events::NewSession,
+            staking::events::{Chilled, EraPaid, SlashReported, Slashed, ValidatorPrefsSet},
+        },
+        format_avail, format_perbill, AvailConfig,
+    },
+};
+use anyhow::{Context, Result};
+use sp_core::H256;
+use std::{cmp::Reverse, collections::BTreeMap, fs, path::Path, str::FromStr};
+use subxt::{blocks::Block, client::OnlineClient, events::Events, utils::AccountId32};
+
+/// Validators we run ourselves, reported on individually and optionally routed to their own
+/// sinks.
+#[derive(Default)]
+pub struct Watchlist {
+    validators: BTreeMap<AccountId32, Vec<SinkConfig>>,
+}
+
+impl Watchlist {
+    /// Loads the watched validators from `--watch` and the `--watch-file` lines, which hold an
+    /// address followed by optional sink specifications. Blank lines and `#` comments are ignored.
+    pub fn load(accounts: &[String], file: Option<&Path>) -> Result<Self> {
+        let mut watchlist = Self::default();
+        for account in accounts {
+            watchlist
+                .validators
+                .entry(parse_account(account)?)
+                .or_default();
+        }
+
+        if let Some(path) = file {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read watchlist {}", path.display()))?;
+            for line in contents.lines() {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                let mut tokens = line.split_whitespace();
+                let Some(account) = tokens.next() else {
+                    continue;
+                };
+                let sinks = tokens
+                    .map(SinkConfig::from_str)
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Invalid sink for watched validator {}", account))?;
+                watchlist
+                    .validators
+                    .entry(parse_account(account)?)
+                    .or_default()
+                    .extend(sinks);
+            }
+        }
+
+        Ok(watchlist)
+    }
+
+    /// Registers the sinks of every watched validator, routed by its address.
+    pub fn add_routes(&self, notifiers: &mut Notifiers, explorer_url: Option<&str>) -> Result<()> {
+        for (account, sinks) in &self.validators {
+            if !sinks.is_empty() {
+                notifiers.add_route(&account.to_string(), sinks, explorer_url)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_watched(&self, account: &AccountId32) -> bool {
+        self.validators.contains_key(account)
+    }
+
+    /// Reports authored blocks and heartbeats per epoch, reward points per era, commission
+    /// changes, chills, slashes and active set changes of the watched validators.
+    pub async fn on_block(
+        &self,
+        client: &OnlineClient<AvailConfig>,
+        block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+        events: &Events<AvailConfig>,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        if self.validators.is_empty() {
+            return Ok(());
+        }
+        let (hash, number) = (block.hash(), block.number());
+        let parent_hash = block.header().parent_hash;
+
+        for prefs_set in decode::find::<ValidatorPrefsSet>(events.iter()) {
+            let prefs_set = prefs_set?;
+            if !self.is_watched(&prefs_set.stash) {
+                continue;
+            }
+            let previous = decode::storage_at(client, parent_hash)
+                .fetch(&api::storage().staking().validators(&prefs_set.stash))
+                .await?;
+            let commission = prefs_set.prefs.commission.0;
+            if previous
+                .as_ref()
+                .is_some_and(|prefs| prefs.commission.0 == commission)
+            {
+                continue;
+            }
+
+            let name = resolve(client, hash, &prefs_set.stash).await;
+            let mut alert = Alert::new(
+                AlertKind::WatchedCommission,
+                Severity::Warning,
+                format!("{} set commission to {}", name, format_perbill(commission)),
+            )
+            .field("Validator", name)
+            .field("Commission", format_perbill(commission));
+            if let Some(previous) = previous {
+                alert = alert.field("Previous", format_perbill(previous.commission.0));
+            }
+            alerts.notify(routed(alert, &prefs_set.stash, number));
+        }
+
+        for chilled in decode::find::<Chilled>(events.iter()) {
+            let chilled = chilled?;
+            if !self.is_watched(&chilled.stash) {
+                continue;
+            }
+            let name = resolve(client, hash, &chilled.stash).await;
+            let alert = Alert::new(
+                AlertKind::WatchedChilled,
+                Severity::Critical,
+                format!("{} was chilled", name),
+            )
+            .field("Validator", name);
+            alerts.notify(routed(alert, &chilled.stash, number));
+        }
+
+        for report in decode::find::<SlashReported>(events.iter()) {
+            let report = report?;
+            if !self.is_watched(&report.validator) {
+                continue;
+            }
+            let name = resolve(client, hash, &report.validator).await;
+            let alert = Alert::new(
+                AlertKind::WatchedSlash,
+                Severity::Critical,
+                format!("Slash reported for {}", name),
+            )
+            .field("Validator", name)
+            .field("Fraction", format_perbill(report.fraction.0))
+            .field("Slash era", report.slash_era.to_string())
+            .dedup_key("watched_slash_reported");
+            alerts.notify(routed(alert, &report.validator, number));
+        }
+
+        for slashed in decode::find::<Slashed>(events.iter()) {
+            let slashed = slashed?;
+            if !self.is_watched(&slashed.staker) {
+                continue;
+            }
+            let name = resolve(client, hash, &slashed.staker).await;
+            let alert = Alert::new(
+                AlertKind::WatchedSlash,
+                Severity::Critical,
+                format!("{} slashed {}", name, format_avail(slashed.amount)),
+            )
+            .field("Validator", name)
+            .field("Amount", format_avail(slashed.amount));
+            alerts.notify(routed(alert, &slashed.staker, number));
+        }
+
+        if let Some(new_session) = decode::find_first::<NewSession>(events.iter())? {
+            self.on_new_session(client, block, new_session.session_index, alerts)
+                .await?;
+        }
+
+        if let Some(era_paid) = decode::find_first::<EraPaid>(events.iter())? {
+            self.on_era_paid(client, hash, number, era_paid.era_index, alerts)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the ended session's authored blocks and heartbeat, and active set changes.
+    async fn on_new_session(
+        &self,
+        client: &OnlineClient<AvailConfig>,
+        block: &Block<AvailConfig, OnlineClient<AvailConfig>>,
+        session_index: u32,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let (hash, number) = (block.hash(), block.number());
+        // ImOnline clears the ended session's counters when it ends, so read them at the parent
+        let ended = session_index.saturating_sub(1);
+        let previous_storage = decode::storage_at(client, block.header().parent_hash);
+        let previous = previous_storage
+            .fetch_or_default(&api::storage().session().validators())
+            .await?;
+        let current = decode::storage_at(client, hash)
+            .fetch_or_default(&api::storage().session().validators())
+            .await?;
+
+        for (auth_index, account) in previous.iter().enumerate() {
+            if !self.is_watched(account) {
+                continue;
+            }
+            let authored = previous_storage
+                .fetch_or_default(&api::storage().im_online().authored_blocks(ended, account))
+                .await?;
+            let heartbeat = previous_storage
+                .fetch(
+                    &api::storage()
+                        .im_online()
+                        .received_heartbeats(ended, auth_index as u32),
+                )
+                .await?
+                .is_some();
+
+            let name = resolve(client, hash, account).await;
+            let (severity, title) = if heartbeat || authored > 0 {
+                (
+                    Severity::Info,
+                    format!("{} authored {} blocks in epoch {}", name, authored, ended),
+                )
+            } else {
+                (
+                    Severity::Warning,
+                    format!(
+                        "{} authored no blocks and missed its heartbeat in epoch {}",
+                        name, ended
+                    ),
+                )
+            };
+            let alert = Alert::new(AlertKind::WatchedEpoch, severity, title)
+                .field("Validator", name)
+                .field("Authored blocks", authored.to_string())
+                .field("Heartbeat", if heartbeat { "received" } else { "missed" })
+                .epoch(ended.into());
+            alerts.notify(routed(alert, account, number));
+        }
+
+        for account in self.validators.keys() {
+            let (severity, change) = match (previous.contains(account), current.contains(account)) {
+                (false, true) => (Severity::Info, "entered"),
+                (true, false) => (Severity::Warning, "left"),
+                _ => continue,
+            };
+            let name = resolve(client, hash, account).await;
+            let alert = Alert::new(
+                AlertKind::WatchedActiveSet,
+                severity,
+                format!(
+                    "{} {} the active set in session {}",
+                    name, change, session_index
+                ),
+            )
+            .field("Validator", name)
+            .field("Session", session_index.to_string())
+            .epoch(session_index.into());
+            alerts.notify(routed(alert, account, number));
+        }
+
+        Ok(())
+    }
+
+    /// Reports the reward points and rank of the watched validators in the paid era.
+    async fn on_era_paid(
+        &self,
+        client: &OnlineClient<AvailConfig>,
+        block_hash: H256,
+        block_number: u32,
+        era: u32,
+        alerts: &mut AlertManager,
+    ) -> Result<()> {
+        let storage = decode::storage_at(client, block_hash);
+        let reward_points = storage
+            .fetch_or_default(&api::storage().staking().eras_reward_points(era))
+            .await?;
+        let mut ranked: Vec<(AccountId32, u32)> = reward_points.individual.into_iter().collect();
+        ranked.sort_by_key(|(_, points)| Reverse(*points));
+
+        for account in self.validators.keys() {
+            let elected = storage
+                .fetch(&api::storage().staking().eras_validator_prefs(era, account))
+                .await?
+                .is_some();
+            let position = ranked.iter().position(|(a, _)| a == account);
+            if !elected && position.is_none() {
+                continue;
+            }
+            let points = position.map(|i| ranked[i].1).unwrap_or_default();
+
+            let name = resolve(client, block_hash, account).await;
+            let severity = if points == 0 {
+                Severity::Warning
+            } else {
+                Severity::Info
+            };
+            let mut alert = Alert::new(
+                AlertKind::WatchedRewardPoints,
+                severity,
+                format!("{} earned {} points in era {}", name, points, era),
+            )
+            .field("Validator", name)
+            .field("Points", points.to_string())
+            .field("Era total", reward_points.total.to_string())
+            .era(era);
+            if let Some(i) = position {
+                alert = alert.field("Rank", format!("{}/{}", i + 1, ranked.len()));
+            }
+            alerts.notify(routed(alert, account, block_number));
+        }
+
+        Ok(())
+    }
+}
+
+/// Routes the alert to the validator's sinks, making its dedup key unique to the validator and
+/// block.
+fn routed(alert: Alert, account: &AccountId32, block_number: u32) -> Alert {
+    let dedup_key = format!("{}:{}:{}", alert.dedup_key, account, block_number);
+    alert
+        .block(block_number)
+        .block_link("Block", block_number)
+        .dedup_key(dedup_key)
+        .route(account.to_string())
+}
+
+fn parse_account(account: &str) -> Result<AccountId32> {
+    AccountId32::from_str(account).with_context(|| format!("Invalid watched validator {}", account))
+}